@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use sentry_core::protocol::{Attachment, EnvelopeItem, Event};
+use sentry_core::types::Uuid;
+use sentry_core::{ClientOptions, Envelope, Hub};
+use tracing::field::{Field, Visit};
+
+/// The name of the field containing the path of a file to attach to
+/// the Sentry event created for a tracing event.
+pub(crate) const ATTACHMENT_PATH_FIELD: &str = "attachment.path";
+
+/// Finds the value of the [ATTACHMENT_PATH_FIELD] of an event.
+#[derive(Default)]
+struct AttachmentPathVisitor {
+    path: Option<String>,
+}
+
+impl Visit for AttachmentPathVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == ATTACHMENT_PATH_FIELD {
+            self.path = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == ATTACHMENT_PATH_FIELD {
+            self.path = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Reads the file referenced by the [ATTACHMENT_PATH_FIELD] of the
+/// tracing `event`, if it has one and the file is no larger than
+/// `max_bytes`.
+pub(crate) fn attachment_from_event(
+    event: &tracing::Event<'_>,
+    max_bytes: u64,
+) -> Option<Attachment> {
+    let mut visitor = AttachmentPathVisitor::default();
+    event.record(&mut visitor);
+    let path = visitor.path?;
+
+    let result = std::fs::metadata(&path).and_then(|metadata| {
+        if metadata.len() > max_bytes {
+            Ok(None)
+        } else {
+            std::fs::read(&path).map(Some)
+        }
+    });
+
+    match result {
+        Ok(Some(buffer)) => Some(Attachment {
+            buffer,
+            filename: Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or(path),
+            ty: None,
+        }),
+        Ok(None) => {
+            eprintln!(
+                "File \"{}\" was not attached to the sentry event because it is larger than {} bytes.",
+                path, max_bytes
+            );
+            None
+        }
+        Err(error) => {
+            eprintln!(
+                "File \"{}\" could not be attached to the sentry event: {}",
+                path, error
+            );
+            None
+        }
+    }
+}
+
+/// Captures the `event` on the `hub`, sending the `attachment` along
+/// with it in the same envelope.
+///
+/// `Hub::capture_event` has no way of including attachments with the
+/// event, and sentry-core 0.22 neither supports attachments on the
+/// scope nor exposes how the client prepares its events. So the event
+/// is prepared here the way `Client::prepare_event` of sentry-core
+/// 0.22 does: it is sampled, the scope of the `hub` is applied to it,
+/// it is processed by the integrations of the client, completed with
+/// the defaults of its options and passed to their `before_send`
+/// callback. Unlike events captured by the client, it does not update
+/// the release health session of the scope, does not become the
+/// [last_event_id](Hub::last_event_id) of the `hub`, and is not given
+/// the SDK info of the client, which is not accessible.
+pub(crate) fn capture_event_with_attachment(
+    hub: &Hub,
+    mut event: Event<'static>,
+    attachment: Attachment,
+) {
+    let client = match hub.client() {
+        Some(client) if client.is_enabled() => client,
+        _ => return,
+    };
+    let options = client.options();
+
+    if options.sample_rate < 1.0 && rand::random::<f32>() >= options.sample_rate {
+        return;
+    }
+    if event.event_id.is_nil() {
+        event.event_id = Uuid::new_v4();
+    }
+
+    // The scope is only accessible mutably, so it is applied within a
+    // temporary scope, leaving the scope of the hub untouched.
+    let mut event = Some(event);
+    hub.with_scope(
+        |scope| event = event.take().and_then(|event| scope.apply_to_event(event)),
+        || {},
+    );
+    let event = match event.and_then(|event| prepare_event(event, options)) {
+        Some(event) => event,
+        None => return,
+    };
+
+    let mut envelope: Envelope = event.into();
+    envelope.add_item(EnvelopeItem::Attachment(attachment));
+    client.send_envelope(envelope);
+}
+
+/// The steps `Client::prepare_event` of sentry-core 0.22 performs on an
+/// event after applying the scope to it.
+fn prepare_event(mut event: Event<'static>, options: &ClientOptions) -> Option<Event<'static>> {
+    for integration in &options.integrations {
+        event = integration.process_event(event, options)?;
+    }
+
+    if event.release.is_none() {
+        event.release = options.release.clone();
+    }
+    if event.environment.is_none() {
+        event.environment = options.environment.clone();
+    }
+    if event.server_name.is_none() {
+        event.server_name = options.server_name.clone();
+    }
+    if event.platform == "other" {
+        event.platform = "native".into();
+    }
+
+    match &options.before_send {
+        Some(before_send) => before_send(event),
+        None => Some(event),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use sentry_core::protocol::{Event, Level};
+    use sentry_core::test::TestTransport;
+    use sentry_core::{ClientOptions, Hub, Integration};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{SentrySpanLayer, TracingIntegration, TracingIntegrationOptions};
+
+    /// Tags the events it processes.
+    struct Tagging;
+
+    impl Integration for Tagging {
+        fn process_event(
+            &self,
+            mut event: Event<'static>,
+            _options: &ClientOptions,
+        ) -> Option<Event<'static>> {
+            event.tags.insert("processed".into(), "yes".into());
+            Some(event)
+        }
+    }
+
+    #[test]
+    fn files_are_attached_to_the_event() {
+        let path = std::env::temp_dir().join("sentry-tracing-attachment.txt");
+        std::fs::write(&path, "attached").unwrap();
+
+        let transport = TestTransport::new();
+        // Events captured from before_send must not block on the one
+        // being sent.
+        let nested = Arc::new(AtomicBool::new(false));
+        let options = ClientOptions {
+            dsn: Some("https://public@sentry.invalid/1".parse().unwrap()),
+            transport: Some(Arc::new(transport.clone())),
+            before_send: Some(Arc::new(move |event| {
+                if !nested.swap(true, Ordering::SeqCst) {
+                    sentry_core::capture_message("Captured from before_send", Level::Info);
+                }
+                Some(event)
+            })),
+            ..ClientOptions::default()
+        }
+        .add_integration(TracingIntegration::new(TracingIntegrationOptions {
+            attach_files: true,
+            ..TracingIntegrationOptions::default()
+        }))
+        .add_integration(Tagging);
+        let hub = Arc::new(Hub::new(Some(Arc::new(options.into())), Arc::default()));
+        hub.configure_scope(|scope| scope.set_tag("scope", "applied"));
+
+        let subscriber = tracing_subscriber::registry().with(SentrySpanLayer::default());
+        tracing::subscriber::with_default(subscriber, || {
+            Hub::run(
+                hub,
+                || tracing::error!(attachment.path = %path.display(), "Generates an event"),
+            )
+        });
+
+        let envelopes = transport.fetch_and_clear_envelopes();
+        assert_eq!(envelopes.len(), 2);
+        let envelope = envelopes
+            .iter()
+            .find(|envelope| envelope.items().count() == 2)
+            .expect("The event is sent with its attachment");
+        let event = envelope.event().unwrap();
+        assert_eq!(event.tags["scope"], "applied");
+        assert_eq!(event.tags["processed"], "yes");
+        assert_eq!(event.platform, "native");
+        assert!(event.extra.is_empty());
+        let attachment = envelope
+            .items()
+            .find_map(|item| match item {
+                sentry_core::protocol::EnvelopeItem::Attachment(attachment) => Some(attachment),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(attachment.filename, "sentry-tracing-attachment.txt");
+        assert_eq!(attachment.buffer, b"attached");
+    }
+}
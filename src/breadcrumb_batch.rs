@@ -0,0 +1,150 @@
+use std::mem;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use sentry_core::protocol::Event;
+use sentry_core::{Breadcrumb, ClientOptions, Hub};
+
+thread_local! {
+    static PENDING: Arc<Mutex<PendingBreadcrumbs>> = register_queue();
+}
+
+/// The queues of all threads, so that they can all be drained by
+/// [flush_all_breadcrumbs()].
+static QUEUES: Mutex<Vec<Weak<Mutex<PendingBreadcrumbs>>>> = Mutex::new(Vec::new());
+
+/// Creates the queue of the current thread, and adds it to the
+/// [QUEUES], forgetting those of threads which have exited.
+fn register_queue() -> Arc<Mutex<PendingBreadcrumbs>> {
+    let queue = Arc::default();
+    let mut queues = QUEUES.lock().unwrap_or_else(PoisonError::into_inner);
+    queues.retain(|queue| queue.strong_count() > 0);
+    queues.push(Arc::downgrade(&queue));
+    queue
+}
+
+/// The breadcrumbs queued on a thread for the
+/// [breadcrumb_batch_size](crate::TracingIntegrationOptions::breadcrumb_batch_size)
+/// option, which are all for the same [Hub].
+#[derive(Default)]
+struct PendingBreadcrumbs {
+    hub: Option<Arc<Hub>>,
+    breadcrumbs: Vec<Breadcrumb>,
+}
+
+impl PendingBreadcrumbs {
+    /// Takes the queued breadcrumbs along with their [Hub].
+    fn take(&mut self) -> Option<(Arc<Hub>, Vec<Breadcrumb>)> {
+        let breadcrumbs = mem::take(&mut self.breadcrumbs);
+        let hub = self.hub.take()?;
+        Some((hub, breadcrumbs)).filter(|(_, breadcrumbs)| !breadcrumbs.is_empty())
+    }
+}
+
+impl Drop for PendingBreadcrumbs {
+    /// Adds the remaining breadcrumbs when the thread exits.
+    fn drop(&mut self) {
+        add_breadcrumbs(self.take());
+    }
+}
+
+/// Adds the `breadcrumbs` to the scope of their [Hub], taking its lock
+/// only once.
+///
+/// This must not be called while a queue is locked, as the
+/// `before_breadcrumb` callback of the client may emit tracing events,
+/// which queue breadcrumbs in turn.
+fn add_breadcrumbs(breadcrumbs: Option<(Arc<Hub>, Vec<Breadcrumb>)>) {
+    if let Some((hub, breadcrumbs)) = breadcrumbs {
+        hub.add_breadcrumb(breadcrumbs);
+    }
+}
+
+/// Queues the `breadcrumb` for the `hub` on the current thread, adding
+/// the queued breadcrumbs to the scope once there are `batch_size` of
+/// them, or before queueing one for a different [Hub].
+pub(crate) fn queue_breadcrumb(hub: Arc<Hub>, breadcrumb: Breadcrumb, batch_size: usize) {
+    let mut unqueued = Some((hub, breadcrumb));
+    // The queue is gone while the thread is exiting, in which case the
+    // breadcrumb is added right away.
+    let flushed = PENDING
+        .try_with(|pending| {
+            let (hub, breadcrumb) = unqueued.take().expect("breadcrumb is only queued once");
+            let mut pending = pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut flushed = Vec::new();
+            if !matches!(&pending.hub, Some(pending_hub) if Arc::ptr_eq(pending_hub, &hub)) {
+                flushed.push(pending.take());
+                pending.hub = Some(hub);
+            }
+            pending.breadcrumbs.push(breadcrumb);
+            if pending.breadcrumbs.len() >= batch_size {
+                flushed.push(pending.take());
+            }
+            flushed
+        })
+        .unwrap_or_default();
+
+    for breadcrumbs in flushed {
+        add_breadcrumbs(breadcrumbs);
+    }
+    if let Some((hub, breadcrumb)) = unqueued {
+        hub.add_breadcrumb(breadcrumb);
+    }
+}
+
+/// Adds the breadcrumbs queued on the current thread to the scope, so
+/// that they are included in an event which is about to be captured.
+pub(crate) fn flush_breadcrumbs() {
+    let breadcrumbs = PENDING.try_with(|pending| take_breadcrumbs(pending));
+    add_breadcrumbs(breadcrumbs.ok().flatten());
+}
+
+/// Includes the breadcrumbs queued on the current thread for the
+/// current [Hub] in an `event` captured by another integration or the
+/// application, which does not flush them before it is captured.
+///
+/// The scope of the [Hub] cannot be modified while an event is being
+/// captured, so the breadcrumbs stay queued, and are passed to the
+/// `before_breadcrumb` callback of the client again when they are
+/// added to the scope.
+pub(crate) fn add_queued_breadcrumbs(event: &mut Event<'static>, options: &ClientOptions) {
+    let queued = PENDING
+        .try_with(|pending| {
+            let pending = pending.lock().unwrap_or_else(PoisonError::into_inner);
+            match &pending.hub {
+                Some(hub) if Arc::ptr_eq(hub, &Hub::current()) => pending.breadcrumbs.clone(),
+                _ => Vec::new(),
+            }
+        })
+        .unwrap_or_default();
+
+    let breadcrumbs = &mut event.breadcrumbs.values;
+    for breadcrumb in queued {
+        let breadcrumb = match &options.before_breadcrumb {
+            Some(before_breadcrumb) => before_breadcrumb(breadcrumb),
+            None => Some(breadcrumb),
+        };
+        breadcrumbs.extend(breadcrumb);
+    }
+    let excess = breadcrumbs.len().saturating_sub(options.max_breadcrumbs);
+    breadcrumbs.drain(..excess);
+}
+
+/// Adds the breadcrumbs queued on all threads to the scope, e.g. before
+/// the client is closed.
+pub(crate) fn flush_all_breadcrumbs() {
+    let queues: Vec<_> = QUEUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect();
+    for queue in queues {
+        add_breadcrumbs(take_breadcrumbs(&queue));
+    }
+}
+
+/// Takes the breadcrumbs of the `queue` along with their [Hub],
+/// releasing its lock before they are added.
+fn take_breadcrumbs(queue: &Mutex<PendingBreadcrumbs>) -> Option<(Arc<Hub>, Vec<Breadcrumb>)> {
+    queue.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
@@ -0,0 +1,227 @@
+use sentry_core::protocol::{Context, Event, Map};
+
+/// The name of the context listing the data removed from an event to
+/// fit into the size budget.
+const TRUNCATED_CONTEXT: &str = "truncated";
+
+/// Removes some data from an event, returning whether there was any.
+type RemoveData = fn(&mut Event<'static>) -> bool;
+
+/// The data removed from an event when it exceeds its size budget, in
+/// the order it is removed.
+const STAGES: &[(&str, RemoveData)] = &[
+    ("extra", remove_extra),
+    ("breadcrumbs.data", remove_breadcrumb_data),
+    ("spans.fields", remove_span_fields),
+];
+
+/// Removes data from the `event` while its serialized size exceeds
+/// `max_bytes`, in the following order:
+///
+/// 1. The extras of the event.
+/// 2. The data of its breadcrumbs.
+/// 3. The fields of its spans: the [span
+///    contexts](crate::TracingIntegrationOptions::span_contexts) and the
+///    fields of the spans in the `tracing` context of panics.
+///
+/// What was removed is listed in the `truncated` context of the event.
+/// Events which are still too large after all stages are sent as they
+/// are.
+pub(crate) fn enforce_size_budget(event: &mut Event<'static>, max_bytes: usize) {
+    let mut removed = Vec::new();
+    for (name, remove) in STAGES {
+        if serialized_size(event) <= max_bytes {
+            break;
+        }
+        if remove(event) {
+            removed.push(serde_json::Value::from(*name));
+        }
+    }
+
+    if !removed.is_empty() {
+        let mut context = Map::new();
+        context.insert("removed".to_owned(), removed.into());
+        event
+            .contexts
+            .insert(TRUNCATED_CONTEXT.to_owned(), Context::Other(context));
+    }
+}
+
+/// The maximum number of characters of the strings of an event
+/// reduced by [minimize_payload()].
+const MINIMAL_STRING_CHARS: usize = 200;
+
+/// Reduces the `event` to the data needed to identify and group the
+/// error, for the
+/// [minimal_payload](crate::TracingIntegrationOptions::minimal_payload)
+/// option:
+///
+/// - Extras, the data of breadcrumbs and all contexts but `trace` are
+///   removed.
+/// - Stacktraces are removed.
+/// - Messages, exception values and tag values are truncated to 200
+///   characters.
+pub(crate) fn minimize_payload(event: &mut Event<'static>) {
+    remove_extra(event);
+    remove_breadcrumb_data(event);
+    event.contexts.retain(|name, _| name == "trace");
+
+    event.stacktrace = None;
+    for thread in event.threads.values.iter_mut() {
+        thread.stacktrace = None;
+        thread.raw_stacktrace = None;
+    }
+    for exception in event.exception.values.iter_mut() {
+        exception.stacktrace = None;
+        exception.raw_stacktrace = None;
+        truncate_option(&mut exception.value);
+    }
+
+    truncate_option(&mut event.message);
+    if let Some(logentry) = &mut event.logentry {
+        truncate(&mut logentry.message);
+    }
+    for breadcrumb in event.breadcrumbs.values.iter_mut() {
+        truncate_option(&mut breadcrumb.message);
+    }
+    for value in event.tags.values_mut() {
+        truncate(value);
+    }
+}
+
+/// Truncates the `string` to [MINIMAL_STRING_CHARS], marking it with
+/// an ellipsis.
+fn truncate(string: &mut String) {
+    if let Some((index, _)) = string.char_indices().nth(MINIMAL_STRING_CHARS) {
+        string.truncate(index);
+        string.push('…');
+    }
+}
+
+fn truncate_option(string: &mut Option<String>) {
+    if let Some(string) = string {
+        truncate(string);
+    }
+}
+
+fn serialized_size(event: &Event<'static>) -> usize {
+    serde_json::to_vec(event).map_or(0, |json| json.len())
+}
+
+fn remove_extra(event: &mut Event<'static>) -> bool {
+    let removed = !event.extra.is_empty();
+    event.extra.clear();
+    removed
+}
+
+fn remove_breadcrumb_data(event: &mut Event<'static>) -> bool {
+    let mut removed = false;
+    for breadcrumb in event.breadcrumbs.values.iter_mut() {
+        removed |= !breadcrumb.data.is_empty();
+        breadcrumb.data.clear();
+    }
+    removed
+}
+
+fn remove_span_fields(event: &mut Event<'static>) -> bool {
+    let contexts = event.contexts.len();
+    event.contexts.retain(|name, _| !name.starts_with("span."));
+    let mut removed = event.contexts.len() < contexts;
+
+    let spans = match event.contexts.get_mut("tracing") {
+        Some(Context::Other(context)) => context.get_mut("spans"),
+        _ => None,
+    };
+    if let Some(serde_json::Value::Array(spans)) = spans {
+        for span in spans.iter_mut().filter_map(|span| span.as_object_mut()) {
+            removed |= span.remove("fields").is_some();
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry_core::protocol::{Event, Level};
+
+    use crate::test::with_captured_options;
+    use crate::TracingIntegrationOptions;
+
+    fn options() -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            max_event_bytes: Some(1),
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    #[test]
+    fn oversized_events_are_truncated() {
+        let captured = with_captured_options(options(), |_| {
+            tracing::info!(id = 1, "Generates a breadcrumb");
+            tracing::error!("Generates an event");
+        });
+
+        let event = &captured.events[0];
+        assert!(event.contexts.contains_key("truncated"));
+        assert!(event.breadcrumbs[0].data.is_empty());
+    }
+
+    #[test]
+    fn events_of_others_are_not_truncated() {
+        let captured = with_captured_options(options(), |sink| {
+            tracing::info!(id = 1, "Generates a breadcrumb");
+            sink.hub().capture_event(Event {
+                level: Level::Error,
+                ..Event::default()
+            });
+        });
+
+        let event = &captured.events[0];
+        assert!(!event.contexts.contains_key("truncated"));
+        assert!(!event.breadcrumbs[0].data.is_empty());
+    }
+
+    #[test]
+    fn only_own_events_are_minimized() {
+        let options = TracingIntegrationOptions {
+            minimal_payload: true,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |sink| {
+            sink.hub()
+                .configure_scope(|scope| scope.set_extra("extra", 1.into()));
+            tracing::error!("Generates an event");
+            sink.hub().capture_event(Event {
+                level: Level::Error,
+                ..Event::default()
+            });
+        });
+
+        assert!(captured.events[0].extra.is_empty());
+        assert!(!captured.events[1].extra.is_empty());
+    }
+
+    #[test]
+    fn span_contexts_are_removed_last() {
+        let options = TracingIntegrationOptions {
+            span_contexts: true,
+            max_event_bytes: Some(2000),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let long = "x".repeat(1000);
+            let span = tracing::info_span!("request", body = %long);
+            let _enter = span.enter();
+            tracing::info!(body = %long, "Generates a breadcrumb");
+            tracing::error!("Generates an event");
+        });
+
+        let event = &captured.events[0];
+        assert!(!event.contexts.contains_key("span.request"));
+        let truncated = serde_json::to_value(&event.contexts["truncated"]).unwrap();
+        assert_eq!(
+            truncated["removed"],
+            serde_json::json!(["breadcrumbs.data", "spans.fields"])
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::Debug;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+/// A source of wall-clock time used to timestamp the events and
+/// breadcrumbs produced by this crate.
+///
+/// The default is [SystemClock]. A custom implementation can be
+/// supplied through
+/// [TracingIntegrationOptions::clock](crate::TracingIntegrationOptions::clock),
+/// for example [ManualClock] in tests, or a clock backed by a
+/// platform specific time API on targets where
+/// [SystemTime::now()] is unavailable.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A [Clock] that reads the system time via [SystemTime::now()].
+///
+/// [SystemTime::now()] panics on `wasm32-unknown-unknown`, where the
+/// `wasm` feature can be enabled to read the time via the browser's
+/// `Date`/`performance` APIs instead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    fn now(&self) -> SystemTime {
+        let since_epoch = web_time::SystemTime::now()
+            .duration_since(web_time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        SystemTime::UNIX_EPOCH + since_epoch
+    }
+}
+
+/// A [Clock] which only moves when it is told to, useful for
+/// producing deterministic timestamps in tests.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    /// Create a new [ManualClock] starting at the specified time.
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Set the current time of this clock.
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+
+    /// Move the current time of this clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use super::{Clock, ManualClock};
+    use crate::test::with_captured_options;
+    use crate::TracingIntegrationOptions;
+
+    #[test]
+    fn manual_clock_only_moves_when_told_to() {
+        let clock = ManualClock::default();
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH);
+
+        clock.advance(Duration::from_secs(5));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(
+            clock.now(),
+            SystemTime::UNIX_EPOCH + Duration::from_millis(5500)
+        );
+
+        let later = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        clock.set(later);
+        assert_eq!(clock.now(), later);
+    }
+
+    #[test]
+    fn events_and_breadcrumbs_are_timestamped_by_the_clock() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let clock = Arc::new(ManualClock::new(start));
+        let options = TracingIntegrationOptions {
+            clock: clock.clone(),
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::info!("Started");
+            clock.advance(Duration::from_secs(2));
+            tracing::error!("Failed");
+        });
+
+        assert_eq!(SystemTime::from(captured.breadcrumbs[0].timestamp), start);
+        assert_eq!(
+            SystemTime::from(captured.events[0].timestamp),
+            start + Duration::from_secs(2)
+        );
+    }
+}
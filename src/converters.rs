@@ -1,11 +1,15 @@
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
 use sentry_backtrace::current_stacktrace;
-use sentry_core::protocol::{Event, Exception};
-use sentry_core::Breadcrumb;
+use sentry_core::protocol::{Context, Event, Exception, Map};
+use sentry_core::types::{DateTime, TimeZone, Utc};
+use sentry_core::{parse_type_from_debug, Breadcrumb};
 use tracing::field::Field;
+use tracing_subscriber::field::RecordFields;
 
-use crate::TracingIntegrationOptions;
+use crate::integration::ENVIRONMENT_FIELD;
+use crate::{FieldDestination, FieldRoute, JsonFieldParsing, TracingIntegrationOptions};
 
 fn convert_tracing_level(level: &tracing::Level) -> sentry_core::Level {
     match level {
@@ -16,69 +20,349 @@ fn convert_tracing_level(level: &tracing::Level) -> sentry_core::Level {
     }
 }
 
-#[derive(Default)]
-struct FieldVisitorConfig {
+/// The options affecting how fields are recorded, borrowed from the
+/// [TracingIntegrationOptions] to avoid copying them for every event
+/// and span.
+///
+/// Create it with [FieldVisitorConfig::new()], or from the options with
+/// `From`.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldVisitorConfig<'a> {
     /// If set to true, ansi escape sequences will be stripped from
     /// string values, and formatted error/debug values.
-    pub strip_ansi_escapes: bool,
+    strip_ansi_escapes: bool,
     ///
     /// If `Some`, values for tracing events with the field name
     /// matching what is specified here will be included in the event
     /// type string: "[target](event_type) tracing event".
-    pub event_type_field: Option<String>,
+    event_type_field: Option<&'a str>,
+    /// If `Some`, the value of the field with this name overrides the
+    /// timestamp of the event.
+    timestamp_field: Option<&'a str>,
+    /// The maximum number of bytes of byte slice values to include
+    /// in their hex encoded preview.
+    bytes_preview_len: usize,
+    /// Which string fields are parsed as json.
+    parse_json_fields: &'a JsonFieldParsing,
+    /// Rules for routing fields into tags, extras or contexts.
+    field_routes: &'a [FieldRoute],
+    /// Fields with a name starting with this prefix are not recorded.
+    private_field_prefix: Option<&'a str>,
+    /// The names which fields are recorded under, by their original
+    /// name.
+    field_renames: &'a HashMap<String, String>,
+    /// Whether the fields are formatted into the message, which is
+    /// not needed for span fields.
+    record_message: bool,
+}
+
+impl<'a> FieldVisitorConfig<'a> {
+    /// Create a new [FieldVisitorConfig] recording fields the same way
+    /// as the integration with the specified `options`.
+    pub fn new(options: &'a TracingIntegrationOptions) -> Self {
+        options.into()
+    }
+
+    /// Sets whether the fields are formatted into the
+    /// [message](FieldVisitorResult::message), which is not needed for
+    /// span fields (defaults to `true`).
+    pub fn record_message(mut self, record_message: bool) -> Self {
+        self.record_message = record_message;
+        self
+    }
+
+    /// The name the `field` is recorded under, taking into account the
+    /// configured renames.
+    fn name(&self, field: &Field) -> &'a str {
+        self.field_renames
+            .get(field.name())
+            .map_or(field.name(), String::as_str)
+    }
+
+    /// The first route matching the field with the specified `name`,
+    /// and the name the field is reported under.
+    fn route<'n>(&self, name: &'n str) -> Option<(&'a FieldDestination, &'n str)> {
+        self.field_routes
+            .iter()
+            .find_map(|route| Some((&route.destination, route.key(name)?)))
+    }
+
+    /// Whether the field with the specified `name` is private.
+    fn is_private(&self, name: &str) -> bool {
+        self.private_field_prefix
+            .is_some_and(|prefix| name.starts_with(prefix))
+    }
 }
 
-impl From<&TracingIntegrationOptions> for FieldVisitorConfig {
-    fn from(integration: &TracingIntegrationOptions) -> Self {
+impl<'a> From<&'a TracingIntegrationOptions> for FieldVisitorConfig<'a> {
+    fn from(integration: &'a TracingIntegrationOptions) -> Self {
         Self {
             strip_ansi_escapes: integration.strip_ansi_escapes,
-            event_type_field: integration.event_type_field.clone(),
+            event_type_field: integration.event_type_field.as_deref(),
+            timestamp_field: integration.timestamp_field.as_deref(),
+            bytes_preview_len: integration.bytes_preview_len,
+            parse_json_fields: &integration.parse_json_fields,
+            field_routes: &integration.field_routes,
+            private_field_prefix: integration.private_field_prefix.as_deref(),
+            field_renames: &integration.field_renames,
+            record_message: true,
         }
     }
 }
 
-#[derive(Default)]
-struct FieldVisitorResult {
+/// Fields added by `tracing-log` to events bridged from the `log`
+/// crate, carrying the metadata of the original log record.
+const LOG_METADATA_FIELDS: &[&str] = &["log.target", "log.module_path", "log.file", "log.line"];
+
+/// Prefix of fields which are assembled into Sentry contexts, in the
+/// form `context.<context name>.<key>`.
+const CONTEXT_FIELD_PREFIX: &str = "context.";
+
+/// Splits a field name in the form `context.<context name>.<key>`
+/// into the context name and key.
+fn context_field(name: &str) -> Option<(&str, &str)> {
+    name.strip_prefix(CONTEXT_FIELD_PREFIX)?.split_once('.')
+}
+
+/// Prefix of boolean fields which are reported as feature flags, in
+/// the form `feature_flag.<flag name>`.
+const FEATURE_FLAG_FIELD_PREFIX: &str = "feature_flag.";
+
+/// The name of the Sentry context listing the evaluated feature flags.
+const FEATURE_FLAGS_CONTEXT: &str = "flags";
+
+/// The name of the feature flag reported by a field in the form
+/// `feature_flag.<flag name>`.
+fn feature_flag_field(name: &str) -> Option<&str> {
+    name.strip_prefix(FEATURE_FLAG_FIELD_PREFIX)
+        .filter(|flag| !flag.is_empty())
+}
+
+/// Whether the callsite with the specified `metadata` has any
+/// `feature_flag.<flag name>` fields.
+pub(crate) fn has_feature_flag_fields(metadata: &tracing::Metadata<'_>) -> bool {
+    metadata
+        .fields()
+        .iter()
+        .any(|field| feature_flag_field(field.name()).is_some())
+}
+
+/// Adds the feature `flags` to the `flags` context of the
+/// `sentry_event`, and as `feature_flag.<flag name>` tags, keeping the
+/// value of flags which the event already has.
+pub(crate) fn add_feature_flags<'f>(
+    sentry_event: &mut Event<'static>,
+    flags: impl IntoIterator<Item = (&'f String, &'f bool)>,
+) {
+    for (flag, &result) in flags {
+        let tag = format!("{}{}", FEATURE_FLAG_FIELD_PREFIX, flag);
+        if sentry_event.tags.contains_key(&tag) {
+            continue;
+        }
+        sentry_event.tags.insert(tag, result.to_string());
+
+        let context = sentry_event
+            .contexts
+            .entry(FEATURE_FLAGS_CONTEXT.to_owned())
+            .or_insert_with(|| Context::Other(Map::new()));
+        if let Context::Other(context) = context {
+            if let serde_json::Value::Array(values) = context
+                .entry("values".to_owned())
+                .or_insert_with(|| serde_json::Value::Array(Vec::new()))
+            {
+                values.push(serde_json::json!({ "flag": flag, "result": result }));
+            }
+        }
+    }
+}
+
+/// Creates the context with the specified `name` from the values of
+/// its fields, using the matching well-known Sentry context type (such
+/// as `device` or `app`) if the values are compatible with it.
+fn context_from_values(name: &str, values: Map<String, serde_json::Value>) -> Context {
+    let mut typed_values = values.clone();
+    typed_values.insert("type".to_owned(), name.into());
+    match serde_json::from_value(typed_values.into_iter().collect()) {
+        Ok(Context::Other(_)) | Err(_) => Context::Other(values),
+        Ok(context) => context,
+    }
+}
+
+/// The values of the fields recorded for a tracing event.
+#[derive(Debug, Default)]
+pub struct FieldVisitorResult {
+    /// The fields formatted as `name=value`, which make up the message.
     pub display_values: Vec<String>,
+    /// The values of the fields by name, except for the fields reported
+    /// elsewhere (in contexts, tags or extras, or as feature flags).
     pub json_values: BTreeMap<String, serde_json::Value>,
+    /// The values of `context.<context name>.<key>` fields and of fields
+    /// routed to contexts, by context name and key.
+    pub contexts: BTreeMap<String, Map<String, serde_json::Value>>,
+    /// The values of boolean `feature_flag.<flag name>` fields, by flag
+    /// name.
+    pub feature_flags: BTreeMap<String, bool>,
+    /// The values of fields routed to tags, by tag name.
+    pub tags: BTreeMap<String, String>,
+    /// The values of fields routed to extras, by extra name.
+    pub extra: BTreeMap<String, serde_json::Value>,
+    /// The target of a record bridged from the `log` crate.
     pub log_target: Option<String>,
+    /// The module path of a record bridged from the `log` crate.
+    pub log_module_path: Option<String>,
+    /// The file of a record bridged from the `log` crate.
+    pub log_file: Option<String>,
+    /// The line of a record bridged from the `log` crate.
+    pub log_line: Option<u64>,
+    /// The value of the
+    /// [event_type_field](crate::TracingIntegrationOptions::event_type_field).
     pub event_type: Option<String>,
+    /// The value of the
+    /// [timestamp_field](crate::TracingIntegrationOptions::timestamp_field).
+    pub timestamp: Option<DateTime<Utc>>,
+    /// The value of the `server_name` field.
+    pub server_name: Option<String>,
+    /// The value of the `dist` field.
+    pub dist: Option<String>,
+    /// The value of the `sentry.environment` field, looked up by its
+    /// original name, whether it is private, renamed or routed.
+    pub environment: Option<String>,
 }
 
 impl FieldVisitorResult {
-    fn message(&self) -> String {
+    /// The message of the event, made up of all its fields.
+    pub fn message(&self) -> String {
         self.display_values.join("\n")
     }
+
+    /// The target of the event, taking into account the original
+    /// target of records bridged from the `log` crate.
+    pub fn target<'a>(&'a self, event: &'a tracing::Event<'_>) -> &'a str {
+        self.log_target
+            .as_deref()
+            .unwrap_or_else(|| event.metadata().target())
+    }
+
+    /// The module path of the event, taking into account the original
+    /// module path of records bridged from the `log` crate.
+    pub fn module_path<'a>(&'a self, event: &'a tracing::Event<'_>) -> Option<&'a str> {
+        self.log_module_path
+            .as_deref()
+            .or_else(|| event.metadata().module_path())
+    }
+
+    /// The fingerprint identifying the callsite of the event (its
+    /// target, file and line), taking into account the original
+    /// location of records bridged from the `log` crate.
+    fn callsite_fingerprint(&self, event: &tracing::Event<'_>) -> Vec<Cow<'static, str>> {
+        let metadata = event.metadata();
+        let file = self.log_file.as_deref().or_else(|| metadata.file());
+        let line = self.log_line.or_else(|| metadata.line().map(u64::from));
+        vec![
+            self.target(event).to_owned().into(),
+            file.unwrap_or_default().to_owned().into(),
+            line.map(|line| line.to_string()).unwrap_or_default().into(),
+        ]
+    }
 }
 
-#[derive(Default)]
-struct FieldVisitor {
-    config: FieldVisitorConfig,
+/// Records the fields of tracing events and spans into a
+/// [FieldVisitorResult], the same way as the integration does, for use
+/// in custom callbacks.
+///
+/// # Examples
+///
+/// ```
+/// use sentry_tracing::{FieldVisitor, FieldVisitorConfig, TracingIntegrationOptions};
+///
+/// fn message(event: &tracing::Event<'_>, options: &TracingIntegrationOptions) -> String {
+///     FieldVisitor::visit_event(event, FieldVisitorConfig::new(options)).message()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FieldVisitor<'a> {
+    config: FieldVisitorConfig<'a>,
     result: FieldVisitorResult,
 }
 
-impl FieldVisitor {
-    fn visit_event(event: &tracing::Event<'_>, config: FieldVisitorConfig) -> FieldVisitorResult {
-        let mut visitor = Self {
+impl<'a> FieldVisitor<'a> {
+    /// Create a new [FieldVisitor] recording fields according to the
+    /// `config`, to be passed to the `record` method of tracing
+    /// events, spans or their values.
+    pub fn new(config: FieldVisitorConfig<'a>) -> Self {
+        Self {
             config,
-            ..Self::default()
-        };
+            result: FieldVisitorResult::default(),
+        }
+    }
+
+    /// Returns the values of the fields recorded so far.
+    pub fn finish(self) -> FieldVisitorResult {
+        self.result
+    }
+
+    /// Records the fields of the tracing `event`.
+    pub fn visit_event(
+        event: &tracing::Event<'_>,
+        config: FieldVisitorConfig<'a>,
+    ) -> FieldVisitorResult {
+        Self::visit(event, config)
+    }
 
-        event.record(&mut visitor);
-        visitor.result
+    /// Records the `fields`, such as the attributes of a new span or
+    /// the values recorded for it later.
+    pub fn visit<R: RecordFields>(
+        fields: &R,
+        config: FieldVisitorConfig<'a>,
+    ) -> FieldVisitorResult {
+        let mut visitor = Self::new(config);
+        fields.record(&mut visitor);
+        visitor.finish()
     }
 
     fn record_json_value<S: serde::Serialize>(&mut self, field: &Field, value: &S) {
+        if field.name() == ENVIRONMENT_FIELD {
+            self.result.environment = match serde_json::to_value(value) {
+                Ok(serde_json::Value::String(environment)) => Some(environment),
+                Ok(value) => Some(value.to_string()),
+                Err(_) => None,
+            };
+        }
+
+        if self.config.is_private(field.name()) {
+            return;
+        }
+
+        let name = self.config.name(field);
         match serde_json::to_value(value) {
             Ok(json_value) => {
-                self.result
-                    .json_values
-                    .insert(field.name().to_owned(), json_value);
+                if self.config.timestamp_field == Some(name) {
+                    if let Some(timestamp) = timestamp_from_value(&json_value) {
+                        self.result.timestamp = Some(timestamp);
+                    }
+                }
+
+                if self.route_value(name, &json_value) {
+                    return;
+                }
+
+                match context_field(name) {
+                    Some((context, key)) => {
+                        self.result
+                            .contexts
+                            .entry(context.to_owned())
+                            .or_default()
+                            .insert(key.to_owned(), json_value);
+                    }
+                    None => {
+                        self.result.json_values.insert(name.to_owned(), json_value);
+                    }
+                }
             }
             Err(error) => {
                 let error = eyre::eyre!(
                     "Error while serializing the \"{}\" field to json: {}",
-                    field.name(),
+                    name,
                     error
                 );
                 tracing::error!(error = ?error)
@@ -86,18 +370,143 @@ impl FieldVisitor {
         }
     }
 
-    fn record_value_message(&mut self, field: &Field, value: &str) {
-        if let Some(field_name) = &self.config.event_type_field {
-            if field.name() == field_name {
-                self.result.event_type = Some(value.to_owned());
+    /// Records the value of the field with the specified `name` in the
+    /// destination of the first configured route matching it, returning
+    /// whether there is one.
+    fn route_value(&mut self, name: &str, json_value: &serde_json::Value) -> bool {
+        let (destination, key) = match self.config.route(name) {
+            Some(route) => route,
+            None => return false,
+        };
+        match destination {
+            FieldDestination::Tag => {
+                let tag = match json_value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                self.result.tags.insert(key.to_owned(), tag);
             }
+            FieldDestination::Extra => {
+                self.result.extra.insert(key.to_owned(), json_value.clone());
+            }
+            FieldDestination::Context(context) => {
+                self.result
+                    .contexts
+                    .entry(context.clone())
+                    .or_default()
+                    .insert(key.to_owned(), json_value.clone());
+            }
+            FieldDestination::Drop => {}
+        }
+        true
+    }
+
+    /// Records a string `value`, which is parsed as json if configured
+    /// for the `field`.
+    fn record_string_value(&mut self, field: &Field, value: &str) {
+        let parsed_json = if self
+            .config
+            .parse_json_fields
+            .matches(self.config.name(field))
+        {
+            serde_json::from_str::<serde_json::Value>(value).ok()
+        } else {
+            None
+        };
+
+        match parsed_json {
+            Some(json_value) => self.record_json_value(field, &json_value),
+            None => self.record_json_value(field, &value),
+        }
+        self.record_value_message(field, value);
+    }
+
+    /// Records the `value` of the `server_name` and `dist` fields, which
+    /// set the corresponding attributes of events, whether they are
+    /// recorded as strings or with `%`.
+    fn record_event_attribute(&mut self, field: &Field, value: &str) {
+        match self.config.name(field) {
+            "server_name" => self.result.server_name = Some(value.to_owned()),
+            "dist" => self.result.dist = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    fn record_value_message(&mut self, field: &Field, value: &str) {
+        if !self.config.record_message || self.config.is_private(field.name()) {
+            return;
+        }
+
+        let name = self.config.name(field);
+
+        // The metadata of bridged log records is reported separately,
+        // rather than cluttering the message.
+        if LOG_METADATA_FIELDS.contains(&name) {
+            return;
+        }
+
+        // Context fields and routed fields are reported elsewhere.
+        if context_field(name).is_some() || self.config.route(name).is_some() {
+            return;
+        }
+
+        if self.config.event_type_field == Some(name) {
+            self.result.event_type = Some(value.to_owned());
         }
         self.result
             .display_values
-            .push(format!("{}={}", field, value));
+            .push(format!("{}={}", name, value));
     }
 }
 
+/// Records the fields of the tracing `event`.
+pub(crate) fn visit_event_fields(
+    event: &tracing::Event<'_>,
+    options: &TracingIntegrationOptions,
+) -> FieldVisitorResult {
+    FieldVisitor::visit_event(event, options.into())
+}
+
+/// Reads a timestamp from unix seconds or milliseconds (as a number
+/// or a string), or an RFC 3339 string.
+///
+/// Numbers of at least 10^11 (which as seconds would be past the year
+/// 5000) are read as milliseconds.
+fn timestamp_from_value(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    let number = match value {
+        serde_json::Value::Number(number) => number.as_f64()?,
+        serde_json::Value::String(string) => match string.parse::<f64>() {
+            Ok(number) => number,
+            Err(_) => return string.parse::<DateTime<Utc>>().ok(),
+        },
+        _ => return None,
+    };
+
+    let seconds = if number.abs() >= 1e11 {
+        number / 1000.0
+    } else {
+        number
+    };
+    if !seconds.is_finite() {
+        return None;
+    }
+    let whole_seconds = seconds.floor();
+    let nanos = ((seconds - whole_seconds) * 1e9) as u32;
+    Utc.timestamp_opt(whole_seconds as i64, nanos).single()
+}
+
+/// Formats a byte slice as its length followed by a hex encoded preview
+/// of at most `preview_len` bytes, e.g. `<1024 bytes: 0a1b2c…>`.
+fn format_bytes_preview(bytes: &[u8], preview_len: usize) -> String {
+    let preview: String = bytes
+        .iter()
+        .take(preview_len)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let ellipsis = if bytes.len() > preview_len { "…" } else { "" };
+    format!("<{} bytes: {}{}>", bytes.len(), preview, ellipsis)
+}
+
 /// Strips ansi color escape codes from string, or returns the
 /// original string if there was problem performing the strip.
 pub fn strip_ansi_codes_from_string(string: &str) -> String {
@@ -110,7 +519,7 @@ pub fn strip_ansi_codes_from_string(string: &str) -> String {
     string.to_owned()
 }
 
-impl tracing::field::Visit for FieldVisitor {
+impl tracing::field::Visit for FieldVisitor<'_> {
     /// Visit a signed 64-bit integer value.
     fn record_i64(&mut self, field: &Field, value: i64) {
         self.record_json_value(field, &value);
@@ -119,30 +528,45 @@ impl tracing::field::Visit for FieldVisitor {
 
     /// Visit an unsigned 64-bit integer value.
     fn record_u64(&mut self, field: &Field, value: u64) {
+        if self.config.name(field) == "log.line" {
+            self.result.log_line = Some(value);
+        }
+
         self.record_json_value(field, &value);
-        self.record_value_message(field, &&format!("{:?}", value));
+        self.record_value_message(field, &format!("{:?}", value));
     }
 
     /// Visit a boolean value.
     fn record_bool(&mut self, field: &Field, value: bool) {
+        let name = self.config.name(field);
+        if let Some(flag) = feature_flag_field(name).filter(|_| self.config.route(name).is_none()) {
+            if !self.config.is_private(field.name()) {
+                self.result.feature_flags.insert(flag.to_owned(), value);
+            }
+            return;
+        }
+
         self.record_json_value(field, &value);
-        self.record_value_message(field, &&format!("{:?}", value));
+        self.record_value_message(field, &format!("{:?}", value));
     }
 
     /// Visit an `&str` value.
     fn record_str(&mut self, field: &Field, value: &str) {
         let value = if self.config.strip_ansi_escapes {
-            strip_ansi_codes_from_string(&value)
+            strip_ansi_codes_from_string(value)
         } else {
             value.to_owned()
         };
 
-        if field.name() == "log.target" {
-            self.result.log_target = Some(value.clone());
+        match self.config.name(field) {
+            "log.target" => self.result.log_target = Some(value.clone()),
+            "log.module_path" => self.result.log_module_path = Some(value.clone()),
+            "log.file" => self.result.log_file = Some(value.clone()),
+            _ => {}
         }
 
-        self.record_json_value(field, &value);
-        self.record_value_message(field, &value);
+        self.record_event_attribute(field, &value);
+        self.record_string_value(field, &value);
     }
 
     /// Visit a type that implements `std::error::Error`.
@@ -158,6 +582,14 @@ impl tracing::field::Visit for FieldVisitor {
         self.record_value_message(field, &message_string);
     }
 
+    /// Visit a byte slice.
+    fn record_bytes(&mut self, field: &Field, value: &[u8]) {
+        let formatted_value = format_bytes_preview(value, self.config.bytes_preview_len);
+
+        self.record_json_value(field, &formatted_value);
+        self.record_value_message(field, &formatted_value);
+    }
+
     /// Visit a type that implements `std::fmt::Debug`.
     fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
         let formatted_value = format!("{:?}", value);
@@ -167,32 +599,293 @@ impl tracing::field::Visit for FieldVisitor {
             formatted_value
         };
 
-        self.record_json_value(field, &message_string);
-        self.record_value_message(field, &message_string);
+        self.record_event_attribute(field, &message_string);
+        self.record_string_value(field, &message_string);
+    }
+}
+
+/// Finds the original target of records bridged from the `log` crate.
+#[derive(Default)]
+struct LogTargetVisitor(Option<String>);
+
+impl tracing::field::Visit for LogTargetVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "log.target" {
+            self.0 = Some(value.to_owned());
+        }
     }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// The target of the tracing `event`, taking into account the original
+/// target of records bridged from the `log` crate, without recording
+/// any other fields.
+pub(crate) fn event_target<'a>(event: &'a tracing::Event<'_>) -> Cow<'a, str> {
+    let metadata = event.metadata();
+    if metadata.fields().field("log.target").is_none() {
+        return Cow::Borrowed(metadata.target());
+    }
+    let mut visitor = LogTargetVisitor::default();
+    event.record(&mut visitor);
+    visitor
+        .0
+        .map_or(Cow::Borrowed(metadata.target()), Cow::Owned)
+}
+
+/// Finds the `error` of events emitted by functions annotated with
+/// `#[instrument(err)]`, which have that as their only field.
+#[derive(Default)]
+struct InstrumentErrorVisitor {
+    error: Option<String>,
+    other_fields: bool,
+}
+
+impl tracing::field::Visit for InstrumentErrorVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "error" {
+            self.error = Some(format!("{:?}", value));
+        } else {
+            self.other_fields = true;
+        }
+    }
+}
+
+/// Returns the formatted error if the `event` was emitted for an error
+/// returned from a function annotated with `#[instrument(err)]`, which
+/// creates the span with the `span_metadata`.
+pub(crate) fn instrument_error(
+    event: &tracing::Event<'_>,
+    span_metadata: &tracing::Metadata<'_>,
+) -> Option<String> {
+    let metadata = event.metadata();
+    if metadata.target() != span_metadata.target() || metadata.file() != span_metadata.file() {
+        return None;
+    }
+
+    let mut visitor = InstrumentErrorVisitor::default();
+    event.record(&mut visitor);
+    if visitor.other_fields {
+        return None;
+    }
+    visitor.error
+}
+
+/// Splits a formatted error into its type and message, where the type
+/// can be determined from the formatting, e.g. `ParseIntError { kind:
+/// InvalidDigit }` or `ConfigError: missing key`. A Display formatted
+/// error is only split if its prefix ends with `Error`, so that
+/// messages such as `Timeout: 30s` are kept whole.
+pub(crate) fn split_error_type(error: &str) -> Option<(&str, &str)> {
+    let starts_uppercase = |ty: &str| ty.chars().next().is_some_and(|c| c.is_ascii_uppercase());
+
+    // Debug formatted error.
+    // The type is trimmed, so the rest is found by stripping it from
+    // the trimmed error rather than by its length.
+    let ty = parse_type_from_debug(error);
+    let rest = error.trim_start().strip_prefix(ty).map(str::trim_start);
+    if starts_uppercase(ty)
+        && rest.is_some_and(|rest| rest.starts_with('{') || rest.starts_with('('))
+    {
+        return Some((ty, error));
+    }
+
+    // Display formatted error with a type prefix.
+    let (ty, message) = error.split_once(": ")?;
+    if starts_uppercase(ty) && ty.ends_with("Error") && !ty.contains(char::is_whitespace) {
+        return Some((ty, message));
+    }
+
+    None
+}
+
+/// The fields recorded for a span, stored in its extensions.
+#[derive(Default)]
+pub(crate) struct SpanFields {
+    pub values: BTreeMap<String, serde_json::Value>,
+    pub feature_flags: BTreeMap<String, bool>,
+    /// The values of the fields reported in the tags, extras and
+    /// contexts of the events captured within the span.
+    pub tags: BTreeMap<String, String>,
+    pub extra: BTreeMap<String, serde_json::Value>,
+    pub contexts: BTreeMap<String, Map<String, serde_json::Value>>,
+    /// The value of the `sentry.environment` field of the span.
+    pub environment: Option<String>,
+}
+
+impl SpanFields {
+    /// Records the specified `fields`, replacing the values of any
+    /// fields which were already recorded.
+    pub fn record<R: RecordFields>(&mut self, fields: &R, options: &TracingIntegrationOptions) {
+        let config = FieldVisitorConfig::new(options).record_message(false);
+        let visitor_result = FieldVisitor::visit(fields, config);
+        if let Some(environment) = visitor_result.environment {
+            self.environment = Some(environment);
+        }
+        self.values.extend(visitor_result.json_values);
+        self.feature_flags.extend(visitor_result.feature_flags);
+        self.tags.extend(visitor_result.tags);
+        self.extra.extend(visitor_result.extra);
+        for (name, values) in visitor_result.contexts {
+            self.contexts.entry(name).or_default().extend(values);
+        }
+    }
+
+    /// Whether no fields were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+            && self.feature_flags.is_empty()
+            && self.tags.is_empty()
+            && self.extra.is_empty()
+            && self.contexts.is_empty()
+            && self.environment.is_none()
+    }
+
+    /// Adds the fields of the span reported in tags, extras and
+    /// contexts to the `sentry_event`, keeping the values which the
+    /// event already has.
+    pub fn add_to_event(&self, sentry_event: &mut Event<'static>) {
+        for (tag, value) in &self.tags {
+            sentry_event
+                .tags
+                .entry(tag.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (key, value) in &self.extra {
+            sentry_event
+                .extra
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (name, values) in &self.contexts {
+            match sentry_event.contexts.get_mut(name) {
+                None => {
+                    let context = context_from_values(name, values.clone());
+                    sentry_event.contexts.insert(name.clone(), context);
+                }
+                Some(Context::Other(context)) => {
+                    for (key, value) in values {
+                        context.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn str_value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).and_then(|value| value.as_str())
+    }
+}
+
+/// Creates an `http` breadcrumb from a span following the
+/// OpenTelemetry conventions for outgoing HTTP requests, with the
+/// `otel.kind = "client"`, `http.method` and `http.url` fields, and
+/// optionally `http.status_code`.
+///
+/// Returns `None` if the span is not a HTTP client span.
+pub(crate) fn http_breadcrumb_from_span(
+    fields: &SpanFields,
+    options: &TracingIntegrationOptions,
+) -> Option<Breadcrumb> {
+    let is_client = fields
+        .str_value("otel.kind")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("client"));
+    if !is_client {
+        return None;
+    }
+
+    let method = fields.values.get("http.method")?;
+    let url = fields.values.get("http.url")?;
+
+    let mut data = BTreeMap::new();
+    data.insert("method".to_owned(), method.clone());
+    data.insert("url".to_owned(), url.clone());
+    if let Some(status_code) = fields.values.get("http.status_code") {
+        data.insert("status_code".to_owned(), status_code.clone());
+    }
+
+    Some(Breadcrumb {
+        ty: "http".into(),
+        category: Some("http".into()),
+        data,
+        timestamp: options.clock.now().into(),
+        ..Default::default()
+    })
 }
 
 /// Creates a breadcrumb from a given tracing event.
+///
+/// The fields which are reported in the contexts of events (such as
+/// `context.<context name>.<key>` fields) are included in the data of
+/// the breadcrumb as `<context name>.<key>`.
 pub fn breadcrumb_from_event(
     event: &tracing::Event<'_>,
     integration: &TracingIntegrationOptions,
 ) -> Breadcrumb {
     let visitor_result = FieldVisitor::visit_event(event, integration.into());
+    let category = visitor_result.target(event).to_owned();
+    let message = visitor_result.message();
+
+    // Breadcrumbs have no tags, extras or contexts, so the fields
+    // reported there are included in the data, prefixed with the name
+    // of their context for contexts.
+    let mut data = visitor_result.json_values;
+    data.extend(
+        visitor_result
+            .tags
+            .into_iter()
+            .map(|(tag, value)| (tag, value.into())),
+    );
+    data.extend(visitor_result.extra);
+    for (name, values) in visitor_result.contexts {
+        data.extend(
+            values
+                .into_iter()
+                .map(|(key, value)| (format!("{}.{}", name, key), value)),
+        );
+    }
 
     Breadcrumb {
         ty: "log".into(),
         level: convert_tracing_level(event.metadata().level()),
-        category: Some(event.metadata().target().into()),
-        message: Some(visitor_result.message()),
-        data: visitor_result.json_values,
-        ..Default::default()
+        category: Some(category),
+        message: Some(message),
+        timestamp: visitor_result
+            .timestamp
+            .unwrap_or_else(|| integration.clock.now().into()),
+        data,
     }
 }
 
+/// The extra marking the events created by this crate while they are
+/// captured, until the integration processes them.
+const OWN_EVENT_EXTRA: &str = "sentry_tracing.own_event";
+
+/// Marks the `event` as created by this crate, right before it is
+/// captured.
+///
+/// The SDK info of the event is left to the client, which lists this
+/// integration in it.
+pub(crate) fn mark_own_event(event: &mut Event<'_>) {
+    event.extra.insert(OWN_EVENT_EXTRA.to_owned(), true.into());
+}
+
+/// Whether the `event` was created by this crate, rather than by
+/// another integration or the application, removing its mark.
+pub(crate) fn take_own_event_mark(event: &mut Event<'_>) -> bool {
+    event.extra.remove(OWN_EVENT_EXTRA).is_some()
+}
+
 /// Creates an event from a given log record.
 ///
 /// If `with_stacktrace` is set to `true` then a stacktrace is attached
 /// from the current frame.
+///
+/// The `server_name` and `dist` fields of the event, if present, set
+/// the corresponding attributes of the Sentry event, and fields in the
+/// form `context.<context name>.<key>` are assembled into the context
+/// with that name.
 pub fn convert_tracing_event(
     event: &tracing::Event<'_>,
     options: &TracingIntegrationOptions,
@@ -215,8 +908,27 @@ pub fn convert_tracing_event(
     exception_type.push(' ');
     exception_type.push_str(exception_source);
 
-    Event {
-        logger: Some("sentry-tracing".into()),
+    let logger = match &options.logger {
+        Some(logger) => logger(event, &visitor_result),
+        None => visitor_result.target(event).to_owned(),
+    };
+
+    let custom_fingerprint = options
+        .fingerprint
+        .as_ref()
+        .and_then(|fingerprint| fingerprint(event, &visitor_result));
+    let fingerprint = match custom_fingerprint {
+        Some(fingerprint) => fingerprint.into_iter().map(Cow::from).collect(),
+        None if options.group_by_callsite => visitor_result.callsite_fingerprint(event).into(),
+        None => Event::default().fingerprint,
+    };
+
+    let mut sentry_event = Event {
+        logger: Some(logger),
+        fingerprint,
+        timestamp: visitor_result
+            .timestamp
+            .unwrap_or_else(|| options.clock.now().into()),
         level: convert_tracing_level(event.metadata().level()),
         exception: vec![Exception {
             ty: exception_type,
@@ -226,10 +938,436 @@ pub fn convert_tracing_event(
             } else {
                 None
             },
-            module: event.metadata().module_path().map(|p| p.to_owned()),
+            module: visitor_result.module_path(event).map(|p| p.to_owned()),
             ..Default::default()
         }]
         .into(),
+        contexts: visitor_result
+            .contexts
+            .into_iter()
+            .map(|(name, values)| {
+                let context = context_from_values(&name, values);
+                (name, context)
+            })
+            .collect(),
+        server_name: visitor_result.server_name.map(Cow::from),
+        dist: visitor_result.dist.map(Cow::from),
+        tags: visitor_result.tags,
+        extra: visitor_result.extra,
         ..Default::default()
+    };
+    add_feature_flags(&mut sentry_event, &visitor_result.feature_flags);
+    sentry_event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::with_captured_options;
+
+    #[test]
+    fn split_error_type_from_debug() {
+        assert_eq!(
+            split_error_type("ParseIntError { kind: InvalidDigit }"),
+            Some(("ParseIntError", "ParseIntError { kind: InvalidDigit }"))
+        );
+        assert_eq!(
+            split_error_type("\tIo(Custom)"),
+            Some(("Io", "\tIo(Custom)"))
+        );
+    }
+
+    #[test]
+    fn split_error_type_from_display() {
+        assert_eq!(
+            split_error_type("ConfigError: missing key"),
+            Some(("ConfigError", "missing key"))
+        );
+        assert_eq!(split_error_type("invalid digit found in string"), None);
+        assert_eq!(split_error_type("could not connect: timed out"), None);
+        assert_eq!(split_error_type("Timeout: 30s"), None);
+        assert_eq!(split_error_type("Failed: connection reset"), None);
+        assert_eq!(split_error_type("Bad Error: value"), None);
+    }
+
+    #[test]
+    fn split_error_type_with_leading_whitespace_and_multibyte_chars() {
+        assert_eq!(split_error_type("\tééé"), None);
+        assert_eq!(split_error_type("  ééé { x }"), None);
+        assert_eq!(split_error_type(""), None);
+    }
+
+    #[test]
+    fn renamed_fields_are_reported_under_their_new_name() {
+        let mut options = TracingIntegrationOptions::default();
+        options
+            .field_renames
+            .insert("req_id".to_owned(), "request_id".to_owned());
+
+        let captured = with_captured_options(options, |_| {
+            tracing::info!(req_id = 7, "Handled");
+            tracing::error!(req_id = 7, "Failed");
+        });
+
+        let breadcrumb = &captured.breadcrumbs[0];
+        assert_eq!(breadcrumb.data.get("request_id"), Some(&7.into()));
+        assert!(!breadcrumb.data.contains_key("req_id"));
+        let exception = &captured.events[0].exception.values[0];
+        assert_eq!(
+            exception.value.as_deref(),
+            Some("message=Failed\nrequest_id=7")
+        );
+    }
+
+    #[test]
+    fn private_fields_are_not_sent() {
+        let options = TracingIntegrationOptions {
+            private_field_prefix: Some("private.".to_owned()),
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(private.token = "secret", user = "alice", "Failed");
+        });
+
+        let breadcrumb = &captured.breadcrumbs[0];
+        assert!(!breadcrumb.data.contains_key("private.token"));
+        assert_eq!(breadcrumb.data.get("user"), Some(&"alice".into()));
+        let message = captured.events[0].exception.values[0].value.as_deref();
+        assert_eq!(message, Some("message=Failed\nuser=alice"));
+    }
+
+    #[test]
+    fn timestamp_field_overrides_the_timestamp() {
+        let options = TracingIntegrationOptions {
+            timestamp_field: Some("timestamp".to_owned()),
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(timestamp = 1_600_000_000_500u64, "Replayed");
+            tracing::error!(timestamp = "2020-09-13T12:26:40Z", "Replayed");
+        });
+
+        let expected = Utc.timestamp_opt(1_600_000_000, 500_000_000).unwrap();
+        assert_eq!(captured.events[0].timestamp, expected);
+        assert_eq!(captured.breadcrumbs[0].timestamp, expected);
+        let expected = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        assert_eq!(captured.events[1].timestamp, expected);
+    }
+
+    #[test]
+    fn timestamp_fields_are_ignored_by_default() {
+        let captured = crate::test::with_captured(|_| {
+            tracing::error!(timestamp = 1_600_000_000u64, "Not replayed");
+        });
+
+        let cutoff = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert!(captured.events[0].timestamp > cutoff);
+        assert!(captured.breadcrumbs[0].timestamp > cutoff);
+    }
+
+    #[test]
+    fn byte_slices_are_previewed() {
+        let options = TracingIntegrationOptions {
+            bytes_preview_len: 2,
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::info!(payload = &b"\x0a\x1b\x2c"[..], short = &b"\x0a"[..]);
+        });
+
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(data.get("payload"), Some(&"<3 bytes: 0a1b…>".into()));
+        assert_eq!(data.get("short"), Some(&"<1 bytes: 0a>".into()));
+    }
+
+    #[test]
+    fn debug_formatted_lists_are_kept() {
+        let options = TracingIntegrationOptions {
+            bytes_preview_len: 2,
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::info!(ids = ?vec![1u32, 2, 3]);
+        });
+
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(data.get("ids"), Some(&"[1, 2, 3]".into()));
+    }
+
+    #[test]
+    fn server_name_and_dist_fields_set_the_event_attributes() {
+        let host = String::from("node-1");
+        let captured = crate::test::with_captured(|_| {
+            tracing::error!(server_name = "node-0", dist = "x86", "Failed");
+            tracing::error!(server_name = %host, dist = %42, "Failed");
+        });
+
+        assert_eq!(captured.events[0].server_name.as_deref(), Some("node-0"));
+        assert_eq!(captured.events[0].dist.as_deref(), Some("x86"));
+        assert_eq!(captured.events[1].server_name.as_deref(), Some("node-1"));
+        assert_eq!(captured.events[1].dist.as_deref(), Some("42"));
+    }
+
+    fn routing_options() -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            field_routes: vec![
+                FieldRoute::new("tag.", FieldDestination::Tag),
+                FieldRoute::new("db.", FieldDestination::Context("db".to_owned())),
+                FieldRoute::new("secret", FieldDestination::Drop),
+            ],
+            span_contexts: true,
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    #[test]
+    fn routed_fields_are_only_reported_in_their_destination() {
+        let captured = with_captured_options(routing_options(), |_| {
+            tracing::error!(
+                tag.user = "alice",
+                db.host = "primary",
+                secret = 1,
+                "Failed"
+            );
+        });
+
+        let event = &captured.events[0];
+        assert_eq!(event.tags["user"], "alice");
+        let message = event.exception.values[0].value.as_deref();
+        assert_eq!(message, Some("message=Failed"));
+
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(data.get("user"), Some(&"alice".into()));
+        assert_eq!(data.get("db.host"), Some(&"primary".into()));
+        assert!(!data.contains_key("tag.user"));
+        assert!(!data.contains_key("secret"));
+    }
+
+    #[test]
+    fn routes_apply_to_span_fields_without_span_contexts() {
+        let options = TracingIntegrationOptions {
+            span_contexts: false,
+            ..routing_options()
+        };
+        let captured = with_captured_options(options, |_| {
+            let span = tracing::info_span!("request", tag.tenant = "acme");
+            let _enter = span.enter();
+            tracing::error!("Failed");
+        });
+
+        assert_eq!(captured.events[0].tags["tenant"], "acme");
+    }
+
+    #[test]
+    fn routes_apply_to_span_fields() {
+        let captured = with_captured_options(routing_options(), |_| {
+            let outer = tracing::info_span!("outer", tag.tenant = "acme", tag.user = "bob");
+            let _outer = outer.enter();
+            let inner = tracing::info_span!("inner", db.host = "replica", secret = 1, id = 7);
+            let _inner = inner.enter();
+            tracing::error!(tag.user = "alice", "Failed");
+        });
+
+        let event = &captured.events[0];
+        assert_eq!(event.tags["tenant"], "acme");
+        assert_eq!(event.tags["user"], "alice");
+        let db = serde_json::to_value(&event.contexts["db"]).unwrap();
+        assert_eq!(db["host"], "replica");
+
+        let inner = serde_json::to_value(&event.contexts["span.inner"]).unwrap();
+        assert_eq!(inner, serde_json::json!({ "type": "unknown", "id": 7 }));
+    }
+
+    #[test]
+    fn context_fields_are_kept_in_breadcrumb_data() {
+        let captured = crate::test::with_captured(|_| {
+            tracing::error!(context.db.host = "primary", "Failed");
+        });
+
+        let db = serde_json::to_value(&captured.events[0].contexts["db"]).unwrap();
+        assert_eq!(db["host"], "primary");
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(data.get("db.host"), Some(&"primary".into()));
+    }
+
+    #[test]
+    fn events_are_grouped_by_their_callsite() {
+        let options = TracingIntegrationOptions {
+            group_by_callsite: true,
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            for user in &["alice", "bob"] {
+                tracing::error!("Failed for {}", user);
+            }
+            tracing::error!("Failed for carol");
+        });
+
+        let fingerprints: Vec<_> = captured.events.iter().map(|e| &e.fingerprint).collect();
+        assert_eq!(fingerprints[0], fingerprints[1]);
+        assert_ne!(fingerprints[0], fingerprints[2]);
+        assert_eq!(fingerprints[0][0], "sentry_tracing::converters::tests");
+        assert_eq!(fingerprints[0][1], file!());
+    }
+
+    #[test]
+    fn events_keep_the_default_fingerprint_unless_grouped_by_callsite() {
+        let captured = crate::test::with_captured(|_| tracing::error!("Failed"));
+
+        let default: Vec<_> = Event::default().fingerprint.into_owned();
+        assert_eq!(captured.events[0].fingerprint.as_ref(), default.as_slice());
+    }
+
+    #[test]
+    fn custom_fingerprint_takes_precedence_over_the_callsite() {
+        let options = TracingIntegrationOptions {
+            group_by_callsite: true,
+            fingerprint: Some(Box::new(|_, fields| {
+                let code = fields.json_values.get("code")?;
+                Some(vec!["http".to_owned(), code.to_string()])
+            })),
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(code = 503, "Unavailable");
+            tracing::error!("No code");
+        });
+
+        assert_eq!(captured.events[0].fingerprint.as_ref(), ["http", "503"]);
+        // Falls back to the callsite when the callback returns `None`.
+        assert_eq!(
+            captured.events[1].fingerprint[0],
+            "sentry_tracing::converters::tests"
+        );
+    }
+
+    #[test]
+    fn the_logger_is_the_target_unless_overridden() {
+        let captured = crate::test::with_captured(|_| {
+            tracing::error!(target: "app::db", "Failed");
+        });
+        assert_eq!(captured.events[0].logger.as_deref(), Some("app::db"));
+
+        let options = TracingIntegrationOptions {
+            logger: Some(Box::new(|event, fields| {
+                match fields.json_values.get("component") {
+                    Some(serde_json::Value::String(component)) => component.clone(),
+                    _ => event.metadata().target().to_owned(),
+                }
+            })),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(target: "app::db", component = "pool", "Failed");
+            tracing::error!(target: "app::db", "Failed");
+        });
+        assert_eq!(captured.events[0].logger.as_deref(), Some("pool"));
+        assert_eq!(captured.events[1].logger.as_deref(), Some("app::db"));
+    }
+
+    #[test]
+    fn json_fields_are_parsed_when_their_name_matches() {
+        let options = TracingIntegrationOptions {
+            parse_json_fields: JsonFieldParsing::Suffix("_json".to_owned()),
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::info!(
+                user_json = r#"{"id": 7, "roles": ["admin", {"scope": "billing"}]}"#,
+                raw = r#"{"id": 7}"#,
+                invalid_json = "{not json",
+            );
+        });
+
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(
+            data.get("user_json"),
+            Some(&serde_json::json!({ "id": 7, "roles": ["admin", { "scope": "billing" }] }))
+        );
+        assert_eq!(data.get("raw"), Some(&r#"{"id": 7}"#.into()));
+        assert_eq!(data.get("invalid_json"), Some(&"{not json".into()));
+    }
+
+    #[test]
+    fn json_fields_are_not_parsed_by_default() {
+        let captured = crate::test::with_captured(|_| {
+            tracing::info!(user_json = "[1, 2]");
+        });
+
+        let data = &captured.breadcrumbs[0].data;
+        assert_eq!(data.get("user_json"), Some(&"[1, 2]".into()));
+    }
+
+    #[test]
+    fn json_field_parsing_selects_fields() {
+        assert!(!JsonFieldParsing::Disabled.matches("user_json"));
+        assert!(JsonFieldParsing::All.matches("user"));
+        let suffix = JsonFieldParsing::Suffix("_json".to_owned());
+        assert!(suffix.matches("user_json"));
+        assert!(!suffix.matches("json_user"));
+    }
+
+    #[test]
+    fn bridged_log_records_are_reported_with_their_original_metadata() {
+        let captured = crate::test::with_captured(|_| {
+            tracing::event!(
+                target: "log",
+                tracing::Level::ERROR,
+                log.target = "app::db",
+                log.module_path = "app::db::pool",
+                log.file = "src/db/pool.rs",
+                log.line = 42u64,
+                "Connection lost"
+            );
+        });
+
+        let breadcrumb = &captured.breadcrumbs[0];
+        assert_eq!(breadcrumb.category.as_deref(), Some("app::db"));
+        assert_eq!(
+            breadcrumb.message.as_deref(),
+            Some("message=Connection lost")
+        );
+        let event = &captured.events[0];
+        assert_eq!(event.logger.as_deref(), Some("app::db"));
+        let exception = &event.exception.values[0];
+        assert_eq!(exception.ty, "[app::db] log event");
+        assert_eq!(exception.module.as_deref(), Some("app::db::pool"));
+        assert_eq!(exception.value.as_deref(), Some("message=Connection lost"));
+    }
+
+    #[test]
+    fn feature_flags_are_reported_in_the_flags_context_and_tags() {
+        let captured = crate::test::with_captured(|_| {
+            let request = tracing::info_span!(
+                "request",
+                feature_flag.new_checkout = true,
+                feature_flag.dark_mode = tracing::field::Empty,
+            );
+            let _enter = request.enter();
+            request.record("feature_flag.dark_mode", false);
+            tracing::error!(feature_flag.new_checkout = false, "Failed");
+        });
+
+        let event = &captured.events[0];
+        assert_eq!(event.tags["feature_flag.new_checkout"], "false");
+        assert_eq!(event.tags["feature_flag.dark_mode"], "false");
+        let flags = serde_json::to_value(&event.contexts["flags"]).unwrap();
+        assert_eq!(
+            flags["values"],
+            serde_json::json!([
+                { "flag": "new_checkout", "result": false },
+                { "flag": "dark_mode", "result": false },
+            ])
+        );
+        assert!(!captured.breadcrumbs[0]
+            .data
+            .contains_key("feature_flag.new_checkout"));
     }
 }
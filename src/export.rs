@@ -0,0 +1,66 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use sentry_core::protocol::Event;
+
+/// The file events are mirrored to for the
+/// [export_path](crate::TracingIntegrationOptions::export_path) option.
+#[derive(Default)]
+pub(crate) struct EventExport {
+    file: Mutex<Option<BufWriter<File>>>,
+}
+
+impl EventExport {
+    /// Opens the file at `path` for appending, creating it if needed.
+    pub fn open(&self, path: &Path) -> io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *self.file.lock().unwrap_or_else(PoisonError::into_inner) = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    /// Appends the `event` to the file as a line of json, if it is open.
+    pub fn write(&self, event: &Event<'static>) {
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        let file = match file.as_mut() {
+            Some(file) => file,
+            None => return,
+        };
+        let result = serde_json::to_writer(&mut *file, event)
+            .map_err(io::Error::from)
+            .and_then(|_| file.write_all(b"\n"))
+            .and_then(|_| file.flush());
+        if let Err(error) = result {
+            eprintln!("sentry-tracing could not export an event: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::with_captured_options;
+    use crate::{DryRun, TracingIntegrationOptions};
+
+    #[test]
+    fn events_are_exported_when_not_sent() {
+        let path = std::env::temp_dir().join("sentry-tracing-export.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let options = TracingIntegrationOptions {
+            export_path: Some(path.clone()),
+            dry_run: DryRun::PrintOnly,
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_options(options, |_| {
+            tracing::error!("Exported");
+        });
+
+        assert!(captured.events.is_empty());
+        let exported = std::fs::read_to_string(&path).unwrap();
+        let lines = exported.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(event["exception"]["values"][0]["value"], "message=Exported");
+    }
+}
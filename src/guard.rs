@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use sentry_core::Hub;
+
+use crate::breadcrumb_batch::flush_all_breadcrumbs;
+use crate::SentrySpanLayer;
+
+/// Closes the client of a [Hub] when dropped, delivering the events
+/// captured on it, so that short lived programs do not exit before
+/// their last events are sent.
+///
+/// When dropped, the release health session of the [Hub] (such as the
+/// one started for a root span by
+/// [track_root_span_sessions](crate::TracingIntegrationOptions::track_root_span_sessions))
+/// is ended, and the client's queue is drained before the client is
+/// shut down. The client does not send anything afterwards, so the
+/// guard is meant to live until the end of the program, see
+/// [close()](crate::close).
+///
+/// # Examples
+///
+/// ```
+/// let _sentry = sentry::init(
+///     sentry::ClientOptions::default().add_integration(sentry_tracing::TracingIntegration::default()),
+/// );
+/// let _guard = sentry_tracing::close_guard(std::time::Duration::from_secs(2));
+///
+/// tracing::error!("Delivered before the program exits");
+/// ```
+#[must_use = "the client is closed when the guard is dropped"]
+#[derive(Debug)]
+pub struct CloseGuard {
+    hub: Arc<Hub>,
+    timeout: Duration,
+}
+
+impl CloseGuard {
+    /// Create a new [CloseGuard] for the specified `hub`, waiting at
+    /// most `timeout` for the events to be delivered when closing its
+    /// client.
+    pub fn new(hub: Arc<Hub>, timeout: Duration) -> Self {
+        Self { hub, timeout }
+    }
+}
+
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        close_hub(&self.hub, self.timeout);
+    }
+}
+
+/// Replays the events buffered by the layer of the current subscriber
+/// (see [SentrySpanLayer::pre_init_buffer()]), ends the release health
+/// session of the `hub` and closes its client, returning whether all
+/// the pending events were delivered within the `timeout`.
+///
+/// `sentry-core` has no way to wait for the pending events without
+/// shutting down the transport, so the client does not send anything
+/// afterwards.
+fn close_hub(hub: &Hub, timeout: Duration) -> bool {
+    SentrySpanLayer::with_current(SentrySpanLayer::replay_pre_init_buffer);
+    flush_all_breadcrumbs();
+    hub.end_session();
+    match hub.client() {
+        Some(client) => client.close(Some(timeout)),
+        None => true,
+    }
+}
+
+/// Create a [CloseGuard] for the current [Hub].
+pub fn close_guard(timeout: Duration) -> CloseGuard {
+    CloseGuard::new(Hub::current(), timeout)
+}
+
+/// Ends the release health session of the current [Hub] (such as the
+/// one started for a root span) and closes its client, delivering the
+/// pending events and waiting at most `timeout`, so that command line
+/// tools can finish with a single call.
+///
+/// Returns whether all the events were delivered in time. Closing is
+/// final: the client does not send anything afterwards, so this is
+/// meant to be called once the program is done.
+///
+/// # Examples
+///
+/// ```
+/// let _sentry = sentry::init(
+///     sentry::ClientOptions::default().add_integration(sentry_tracing::TracingIntegration::default()),
+/// );
+///
+/// tracing::error!("Delivered before the program exits");
+/// sentry_tracing::close(std::time::Duration::from_secs(2));
+/// ```
+pub fn close(timeout: Duration) -> bool {
+    close_hub(&Hub::current(), timeout)
+}
+
+/// Installs a Ctrl-C (`SIGINT`) handler which, like dropping a
+/// [CloseGuard] for the main [Hub], ends its release health session
+/// (such as the one of a root span) and closes its client, delivering
+/// the pending events and waiting at most `timeout`, before exiting
+/// the process with status `130`.
+///
+/// **Feature:** `ctrlc` (*disabled by default*)
+///
+/// Interrupted programs do not unwind, so guards such as the one
+/// returned by `sentry::init` are never dropped, and events which are
+/// still queued are lost. Returns an error if a Ctrl-C handler has
+/// already been set.
+#[cfg(feature = "ctrlc")]
+pub fn close_on_ctrlc(timeout: Duration) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(move || {
+        drop(CloseGuard::new(Hub::main(), timeout));
+        std::process::exit(130);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use sentry_core::Hub;
+
+    use crate::test::{with_captured, with_captured_options};
+    use crate::TracingIntegrationOptions;
+
+    #[test]
+    fn close_delivers_the_pending_events_and_closes_the_client() {
+        let captured = with_captured(|_| {
+            tracing::error!("Delivered");
+            assert!(super::close(Duration::from_secs(1)));
+            tracing::error!("Dropped");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("message=Delivered")
+        );
+    }
+
+    #[test]
+    fn close_guard_closes_the_client_when_dropped() {
+        let captured = with_captured(|sink| {
+            let guard = super::CloseGuard::new(sink.hub().clone(), Duration::from_secs(1));
+            tracing::error!("Delivered");
+            drop(guard);
+            tracing::error!("Dropped");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+    }
+
+    #[test]
+    fn close_adds_the_breadcrumbs_queued_on_other_threads() {
+        let options = TracingIntegrationOptions {
+            breadcrumb_batch_size: Some(10),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |sink| {
+            let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+            let hub = sink.hub().clone();
+            let (queued_tx, queued_rx) = mpsc::channel();
+            let (closed_tx, closed_rx) = mpsc::channel::<()>();
+            let worker = thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    Hub::run(hub, || tracing::info!("Queued"));
+                });
+                queued_tx.send(()).unwrap();
+                closed_rx.recv().unwrap();
+            });
+
+            queued_rx.recv().unwrap();
+            assert!(sink.take().breadcrumbs.is_empty());
+            assert!(super::close(Duration::from_secs(1)));
+            let breadcrumbs = sink.take().breadcrumbs;
+
+            closed_tx.send(()).unwrap();
+            worker.join().unwrap();
+            assert_eq!(breadcrumbs.len(), 1);
+            assert_eq!(breadcrumbs[0].message.as_deref(), Some("message=Queued"));
+        });
+
+        assert!(captured.breadcrumbs.is_empty());
+    }
+}
@@ -1,28 +1,575 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use sentry_core::protocol::Event;
 use sentry_core::{ClientOptions, Integration};
-use tracing::Level;
-use tracing_subscriber::EnvFilter;
+use tracing::{Level, Metadata, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::{EnvFilter, Registry};
+
+use crate::breadcrumb_batch::add_queued_breadcrumbs;
+use crate::budget::{enforce_size_budget, minimize_payload};
+use crate::converters::{
+    event_target, has_feature_flag_fields, take_own_event_mark, visit_event_fields,
+    FieldVisitorResult, SpanFields,
+};
+use crate::export::EventExport;
+use crate::layer::CAPTURE;
+use crate::panic::install_panic_hook;
+use crate::watchdog::SpanWatchdog;
+use crate::{Clock, SampleRates, SentrySpanLayer, SystemClock};
+
+/// The name of the field excluding a span from the data captured for
+/// it, see [span_filter](TracingIntegrationOptions::span_filter).
+const SKIP_FIELD: &str = "sentry.skip";
+
+/// The name of the field overriding the environment of the events
+/// captured within a span, see [SentrySpanLayer].
+pub(crate) const ENVIRONMENT_FIELD: &str = "sentry.environment";
+
+/// Controls printing of the data produced by this integration, for
+/// verifying what would be sent to Sentry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DryRun {
+    /// Data is only sent to Sentry.
+    Disabled,
+    /// Data is pretty printed to stderr, and also sent to Sentry.
+    Print,
+    /// Data is pretty printed to stderr, and not sent to Sentry.
+    PrintOnly,
+}
+
+impl DryRun {
+    /// The environment variable read by [DryRun::from_env()].
+    pub const ENV_VAR: &'static str = "SENTRY_TRACING_DRY_RUN";
+
+    /// Reads the mode from the `SENTRY_TRACING_DRY_RUN` environment
+    /// variable, where `print` selects [DryRun::Print], `only`
+    /// selects [DryRun::PrintOnly], and anything else (or the
+    /// variable being unset) selects [DryRun::Disabled].
+    pub fn from_env() -> Self {
+        match std::env::var(Self::ENV_VAR).as_deref() {
+            Ok("print") => Self::Print,
+            Ok("only") => Self::PrintOnly,
+            _ => Self::Disabled,
+        }
+    }
+
+    /// Whether data should be printed.
+    pub fn print(self) -> bool {
+        self != Self::Disabled
+    }
+
+    /// Whether data should be sent to Sentry.
+    pub fn send(self) -> bool {
+        self != Self::PrintOnly
+    }
+}
+
+/// Selects string fields whose values are parsed as json, so that they
+/// are included in Sentry data as structured values.
+///
+/// Values which fail to parse are recorded as strings.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum JsonFieldParsing {
+    /// No fields are parsed.
+    #[default]
+    Disabled,
+    /// All string fields are parsed.
+    All,
+    /// Fields with a name ending in the specified suffix (e.g. `_json`)
+    /// are parsed.
+    Suffix(String),
+}
+
+impl JsonFieldParsing {
+    /// Whether the field with the specified `name` should be parsed.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Disabled => false,
+            Self::All => true,
+            Self::Suffix(suffix) => name.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+/// Determines the transaction name of the Sentry events captured
+/// within spans, from the span the tracing event occurred in and its
+/// ancestors.
+#[derive(Default)]
+pub enum TransactionName {
+    /// The transaction name is not set.
+    #[default]
+    Disabled,
+    /// The name of the outermost span.
+    RootSpan,
+    /// The name of the nearest span which is part of an OpenTelemetry
+    /// trace. Without the `opentelemetry` feature, no span is, so the
+    /// transaction name is not set.
+    NearestTraced,
+    /// The name of the nearest span whose metadata matches the
+    /// predicate.
+    NearestMatching(Box<dyn Fn(&Metadata<'_>) -> bool + Send + Sync>),
+    /// The value of the [TransactionName::FIELD] field of the nearest
+    /// span which has one.
+    Field,
+    /// The name returned by the callback, which is given the metadata
+    /// of the span the event occurred in followed by its ancestors.
+    #[allow(clippy::type_complexity)]
+    Callback(Box<dyn Fn(&[&'static Metadata<'static>]) -> Option<String> + Send + Sync>),
+}
+
+impl TransactionName {
+    /// The name of the field read by [TransactionName::Field].
+    pub const FIELD: &'static str = "sentry.name";
+}
+
+impl fmt::Debug for TransactionName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disabled => f.write_str("Disabled"),
+            Self::RootSpan => f.write_str("RootSpan"),
+            Self::NearestTraced => f.write_str("NearestTraced"),
+            Self::NearestMatching(_) => f.write_str("NearestMatching(..)"),
+            Self::Field => f.write_str("Field"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Determines the culprit of the Sentry events captured within spans,
+/// from the span the tracing event occurred in.
+#[derive(Default)]
+pub enum SpanCulprit {
+    /// The culprit is not derived from the span.
+    #[default]
+    Disabled,
+    /// The name of the span, followed by the value of its
+    /// [SpanCulprit::DESCRIPTION_FIELD] field if it has one, e.g.
+    /// `db.query: SELECT * FROM users`.
+    Span,
+    /// The culprit returned by the callback, which is given the
+    /// metadata and recorded fields of the span.
+    #[allow(clippy::type_complexity)]
+    Callback(
+        Box<
+            dyn Fn(
+                    &'static Metadata<'static>,
+                    &BTreeMap<String, serde_json::Value>,
+                ) -> Option<String>
+                + Send
+                + Sync,
+        >,
+    ),
+}
+
+impl SpanCulprit {
+    /// The name of the field describing the span, which is included
+    /// in the culprit by [SpanCulprit::Span].
+    pub const DESCRIPTION_FIELD: &'static str = "description";
+}
+
+impl fmt::Debug for SpanCulprit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disabled => f.write_str("Disabled"),
+            Self::Span => f.write_str("Span"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Where the value of a field is reported in Sentry data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldDestination {
+    /// The value is reported as a tag of events.
+    Tag,
+    /// The value is reported as an extra of events.
+    Extra,
+    /// The value is reported in the event context with the specified
+    /// name.
+    Context(String),
+    /// The value is not reported at all.
+    Drop,
+}
+
+/// Routes fields with a name starting with `prefix` to a
+/// [FieldDestination].
+///
+/// The field is reported under its name with the `prefix` removed, or
+/// under its full name if the prefix is the full name of the field.
+/// Routes apply to the fields of events and spans: the fields of spans
+/// are reported on the events captured within them, unless the event
+/// or a nearer span has a value for the same key. Breadcrumbs have no
+/// tags, extras or contexts, so fields routed there are included in
+/// their data (prefixed with the context name for contexts). Routed
+/// fields are left out of the event message and of the `span.<name>`
+/// contexts, and dropped fields are not included anywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRoute {
+    /// The prefix of the names of the routed fields.
+    pub prefix: String,
+    /// Where the routed fields are reported.
+    pub destination: FieldDestination,
+}
+
+impl FieldRoute {
+    /// Create a new [FieldRoute] for the fields with a name starting
+    /// with `prefix`.
+    pub fn new(prefix: impl Into<String>, destination: FieldDestination) -> Self {
+        Self {
+            prefix: prefix.into(),
+            destination,
+        }
+    }
+
+    /// The name which the field with the specified `name` is reported
+    /// under, if it is routed by this rule.
+    pub fn key<'a>(&self, name: &'a str) -> Option<&'a str> {
+        match name.strip_prefix(self.prefix.as_str())? {
+            "" => Some(name),
+            key => Some(key),
+        }
+    }
+}
 
 /// Integration that performs
-#[derive(Debug)]
 pub struct TracingIntegrationOptions {
     /// The sentry specific tracing span/event level filter (defaults to `info`).
     pub filter: EnvFilter,
     /// If set to `true`, breadcrumbs will be emitted. (defaults to `true`).
     pub emit_breadcrumbs: bool,
+    /// If `Some`, breadcrumbs are queued on the thread they are created
+    /// on, and added to the scope in batches of this size, to reduce
+    /// the contention on the lock of the [Hub](sentry_core::Hub) at
+    /// high volumes. Queued breadcrumbs are also added before an event
+    /// is captured on the same thread, and when the thread exits.
+    /// Events captured by others on the same thread include them too.
+    /// [close()](crate::close) adds the breadcrumbs queued on all
+    /// threads. Until then, they are missing from
+    /// events captured on other threads sharing the
+    /// [Hub](sentry_core::Hub). (defaults to `None`).
+    pub breadcrumb_batch_size: Option<usize>,
+    /// If set to `false`, spans are ignored: no fields are recorded for
+    /// them, and events are not correlated with the spans they occur
+    /// in, as with the [SentryLayer](crate::SentryLayer).
+    /// The options which rely on spans (such as
+    /// [span_contexts](Self::span_contexts) or
+    /// [track_root_span_sessions](Self::track_root_span_sessions)) have
+    /// no effect. (defaults to `true`).
+    pub record_spans: bool,
+    /// If set to `true`, an `http` breadcrumb will be emitted when a
+    /// span for an outgoing HTTP request (with `otel.kind = "client"`,
+    /// `http.method` and `http.url` fields) closes. Like the
+    /// breadcrumbs of events, they require
+    /// [emit_breadcrumbs](Self::emit_breadcrumbs), and the span must
+    /// pass the [filter](Self::filter). (defaults to `false`).
+    pub emit_http_breadcrumbs: bool,
+    /// If set to `true`, a release health session is started on the
+    /// current [Hub](sentry_core::Hub) whenever a root span is created,
+    /// and ended when it closes. Sessions in which error events were
+    /// captured are reported as errored. (defaults to `false`).
+    ///
+    /// A [Hub](sentry_core::Hub) only tracks one session at a time, so
+    /// concurrent root spans (such as requests in a server) should each
+    /// run with their own [Hub](sentry_core::Hub).
+    pub track_root_span_sessions: bool,
+    /// If `Some`, a warning event is captured for spans which stay open
+    /// longer than this, with the fields of the span and how long it
+    /// has been open (`duration_ms`, in milliseconds) in the
+    /// `slow_span` context, to catch hangs and stuck jobs. Each span is
+    /// reported at most once, and the open spans are checked from a
+    /// background thread, so the event may be delayed by up to a
+    /// quarter of the threshold (at most a second). (defaults to
+    /// `None`).
+    pub slow_span_threshold: Option<std::time::Duration>,
+    /// If `Some`, spans which stay open longer than this are considered
+    /// abandoned (e.g. leaked guards or aborted tasks), and are no
+    /// longer watched for the
+    /// [slow_span_threshold](Self::slow_span_threshold), releasing the
+    /// fields and [Hub](sentry_core::Hub) held for them, and a warning
+    /// is printed to stderr. Without it, the watchdog holds on to
+    /// spans which never close for the lifetime of the integration.
+    /// Spans are only released once they have been reported, so a
+    /// ttl shorter than the threshold releases them right after that,
+    /// and it has no effect without a
+    /// [slow_span_threshold](Self::slow_span_threshold). (defaults to
+    /// `None`).
+    pub abandoned_span_ttl: Option<std::time::Duration>,
+    /// If set to `true`, the file referenced by the `attachment.path`
+    /// field of a tracing event is attached to the Sentry event
+    /// created for it. The client cannot send attachments, so such
+    /// events are sent by the integration, after going through the
+    /// same processing, but they do not update the release health
+    /// session. (defaults to `false`).
+    pub attach_files: bool,
+    /// Files larger than this many bytes are not attached to events
+    /// (defaults to 1 MiB).
+    pub max_attachment_bytes: u64,
+    /// If `Some`, data is removed from the events created by this
+    /// integration whose serialized size exceeds this many bytes, so
+    /// that they are not rejected for being oversized. Extras are
+    /// removed first, then the data of breadcrumbs, then the fields of
+    /// spans, and what was removed is listed in the `truncated` context
+    /// of the event. Events of other integrations are left as they are.
+    /// (defaults to `None`).
+    pub max_event_bytes: Option<usize>,
+    /// If set to `true`, the events created by this integration are
+    /// reduced to the data needed to identify and group errors, for
+    /// deployments where bandwidth to Sentry is expensive: extras, the
+    /// data of breadcrumbs, contexts other than `trace` and stacktraces
+    /// are removed, and messages, exception values and tag values are
+    /// truncated to 200 characters. Events of other integrations are
+    /// left as they are. (defaults to `false`).
+    pub minimal_payload: bool,
+    /// If `Some`, the events created by this integration (for tracing
+    /// events, panics and slow spans) are also appended to the file at
+    /// this path as lines of json when they are captured, before the
+    /// scope and the other integrations are applied to them. Combined
+    /// with [DryRun::PrintOnly], the instrumentation can be verified
+    /// without network access. Like capturing, this requires a client
+    /// with a DSN and a transport (which need not be reachable), and
+    /// stops once the client is closed. The file is created if it does
+    /// not exist. If it cannot be opened, a warning is printed to
+    /// stderr. (defaults to `None`).
+    pub export_path: Option<std::path::PathBuf>,
+    /// If set to `true`, a subscriber consisting of the `Registry`
+    /// and the [SentrySpanLayer] is installed as the global default
+    /// subscriber when the integration is set up, for applications
+    /// which do not set up a subscriber themselves. If a global default
+    /// subscriber is already set, it is kept and a warning is printed
+    /// to stderr. (defaults to `false`).
+    pub install_global_subscriber: bool,
+    /// If set to `true`, a panic hook is installed which captures
+    /// panics as events, including the stack of tracing spans (and
+    /// their fields) the panic occurred in. This replaces the
+    /// `sentry-panic` integration, which should not be used at the
+    /// same time. (defaults to `false`).
+    pub capture_panics: bool,
     /// If set to `true` error events will be sent for errors in the log. (defaults to `true`).
+    ///
+    /// Sent events are counted as errors of the active release health
+    /// session of the [Hub](sentry_core::Hub), so disabling this also
+    /// stops errors in the log from marking the session as errored.
     pub emit_error_events: bool,
+    /// The fraction of error events which are sent, independent of
+    /// the [sample_rate](ClientOptions::sample_rate) of the client,
+    /// which can be overridden per target, using the original target
+    /// of records bridged from the `log` crate (defaults to `1.0`).
+    pub error_event_sample_rate: SampleRates,
     /// If set to `true` warning events will be sent for warnings in the log. (defaults to `false`).
+    ///
+    /// Like error events, sent warning events are reported as
+    /// exceptions, and so are also counted as errors of the active
+    /// release health session.
     pub emit_warning_events: bool,
     /// If set to `true` current stacktrace will be resolved and attached
     /// to each event. (expensive, defaults to `true`).
     pub attach_stacktraces: bool,
+    /// Prefixes of the functions in stack traces which are not part of
+    /// the application, added to the
+    /// [in_app_exclude](ClientOptions::in_app_exclude) of the client
+    /// (defaults to the `tracing` and `log` crates).
+    pub in_app_exclude: Vec<&'static str>,
+    /// Functions which are the border between the application and the
+    /// logging machinery in stack traces, added to the
+    /// [extra_border_frames](ClientOptions::extra_border_frames) of
+    /// the client (defaults to the dispatch functions of the `tracing`
+    /// and `log` crates).
+    pub extra_border_frames: Vec<&'static str>,
     /// If set to true, ansi escape sequences will be stripped from
     /// string values, and formatted error/debug values.
     pub strip_ansi_escapes: bool,
+    /// Byte slice values are recorded as their length and a hex
+    /// encoded preview of at most this many bytes (defaults to `32`).
+    pub bytes_preview_len: usize,
+    /// Which string fields have their values parsed as json (defaults
+    /// to [JsonFieldParsing::Disabled]).
+    pub parse_json_fields: JsonFieldParsing,
     /// If `Some`, values for tracing events with the field name
     /// matching what is specified here will be included in the event
     /// type string: "[target](event_type) tracing event".
     pub event_type_field: Option<String>,
+    /// If `Some`, the value of the field with this name (e.g.
+    /// `timestamp`) overrides the timestamp of events and breadcrumbs,
+    /// for logs which are replayed or forwarded with their original
+    /// timestamps. Unix seconds or milliseconds, and RFC 3339 strings
+    /// are supported (defaults to `None`).
+    pub timestamp_field: Option<String>,
+    /// Rules for routing fields into tags, extras or contexts, or
+    /// dropping them, for the fields of events and spans (see
+    /// [FieldRoute]). The first matching rule applies, and fields
+    /// which match no rule are only included in the event message
+    /// (defaults to no rules).
+    pub field_routes: Vec<FieldRoute>,
+    /// Renames fields, by their original name, so that naming can be
+    /// normalized across crates (e.g. `req_id` to `request_id`). The
+    /// new names apply to all other options (defaults to no renames).
+    pub field_renames: HashMap<String, String>,
+    /// If `Some`, fields with a name starting with this prefix (e.g.
+    /// `private.`) are local only: they are not included in any data
+    /// sent to Sentry, but are still seen by other layers (defaults to
+    /// `None`).
+    pub private_field_prefix: Option<String>,
+    /// If set, this is called with each tracing event and the fields
+    /// recorded for it, and returning `false` drops the event so that
+    /// neither a Sentry event nor a breadcrumb is created for it.
+    /// Unlike the [filter](Self::filter), this can match on the values
+    /// of fields. It is not called for events which would be neither,
+    /// e.g. when [emit_breadcrumbs](Self::emit_breadcrumbs),
+    /// [emit_error_events](Self::emit_error_events) and
+    /// [emit_warning_events](Self::emit_warning_events) are disabled.
+    /// (defaults to `None`).
+    #[allow(clippy::type_complexity)]
+    pub event_filter:
+        Option<Box<dyn Fn(&tracing::Event<'_>, &FieldVisitorResult) -> bool + Send + Sync>>,
+    /// If set, this is called with the metadata and recorded fields of
+    /// spans, and returning `false` excludes the span from http
+    /// breadcrumbs, span contexts, span culprits, span environments,
+    /// feature flags and slow span reports, e.g. for health check
+    /// requests (defaults to `None`).
+    ///
+    /// Spans with a `sentry.skip = true` field are always excluded, so
+    /// that libraries can exclude their noisy internal spans, e.g. with
+    /// `#[instrument(fields(sentry.skip = true))]`.
+    #[allow(clippy::type_complexity)]
+    pub span_filter: Option<
+        Box<
+            dyn Fn(&'static Metadata<'static>, &BTreeMap<String, serde_json::Value>) -> bool
+                + Send
+                + Sync,
+        >,
+    >,
+    /// If set, this is called with each tracing event which is
+    /// captured as a Sentry event, and the fields recorded for it, to
+    /// determine the logger of the Sentry event. Otherwise the target
+    /// of the tracing event is used. (defaults to `None`).
+    #[allow(clippy::type_complexity)]
+    pub logger:
+        Option<Box<dyn Fn(&tracing::Event<'_>, &FieldVisitorResult) -> String + Send + Sync>>,
+    /// If set to `true`, the fields of each span an event was captured
+    /// in (and its ancestors) are included in the event as a context
+    /// named `span.<span name>`. (defaults to `false`).
+    pub span_contexts: bool,
+    /// If set to `true`, the [span contexts](Self::span_contexts) also
+    /// include the source file (`code.filepath`), line (`code.lineno`)
+    /// and target (`target`) of the callsite which created the span.
+    /// (defaults to `false`).
+    pub span_callsites: bool,
+    /// How the transaction name of events captured within spans is
+    /// determined (defaults to [TransactionName::Disabled]).
+    pub transaction_name: TransactionName,
+    /// How the culprit of events captured within spans is derived from
+    /// the span (defaults to [SpanCulprit::Disabled]).
+    pub span_culprit: SpanCulprit,
+    /// If set to `true`, the error events emitted by functions
+    /// annotated with `#[instrument(err)]` are reported with the type
+    /// of the error (parsed from its formatting, or named after the
+    /// function), the module of the function, and the function as
+    /// their culprit. These events are recognized by their only field
+    /// being `error`, and by sharing the target and file of the span
+    /// they occur in. (defaults to `false`).
+    pub instrument_errors: bool,
+    /// If set to `true`, the fingerprint of events is set to their
+    /// callsite (target, file and line), so that all events from one
+    /// log statement are grouped into a single issue, regardless of
+    /// the values in their message. (defaults to `false`).
+    pub group_by_callsite: bool,
+    /// If set, this is called with each tracing event which is
+    /// captured as a Sentry event, and the fields recorded for it. When
+    /// it returns `Some`, the returned values are used as the
+    /// fingerprint of the Sentry event, taking precedence over
+    /// [group_by_callsite](Self::group_by_callsite). (defaults to `None`).
+    #[allow(clippy::type_complexity)]
+    pub fingerprint: Option<
+        Box<dyn Fn(&tracing::Event<'_>, &FieldVisitorResult) -> Option<Vec<String>> + Send + Sync>,
+    >,
+    /// The source of time used to timestamp events and breadcrumbs
+    /// (defaults to [SystemClock]).
+    pub clock: Arc<dyn Clock>,
+    /// If set to `true`, inconsistencies in the span data of the
+    /// subscriber are reported as warnings on stderr, and the affected
+    /// operation is skipped. If set to `false`, they cause a panic,
+    /// which can help with debugging the subscriber stack. (defaults
+    /// to `true`).
+    pub resilient: bool,
+    /// Print events and breadcrumbs to stderr instead of, or in
+    /// addition to sending them (defaults to [DryRun::from_env()]).
+    pub dry_run: DryRun,
+}
+
+impl fmt::Debug for TracingIntegrationOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("TracingIntegrationOptions");
+        debug
+            .field("filter", &self.filter)
+            .field("emit_breadcrumbs", &self.emit_breadcrumbs)
+            .field("breadcrumb_batch_size", &self.breadcrumb_batch_size)
+            .field("record_spans", &self.record_spans)
+            .field("emit_http_breadcrumbs", &self.emit_http_breadcrumbs)
+            .field("track_root_span_sessions", &self.track_root_span_sessions)
+            .field("slow_span_threshold", &self.slow_span_threshold)
+            .field("abandoned_span_ttl", &self.abandoned_span_ttl)
+            .field("attach_files", &self.attach_files)
+            .field("max_attachment_bytes", &self.max_attachment_bytes)
+            .field("max_event_bytes", &self.max_event_bytes)
+            .field("minimal_payload", &self.minimal_payload)
+            .field("export_path", &self.export_path)
+            .field("install_global_subscriber", &self.install_global_subscriber)
+            .field("capture_panics", &self.capture_panics)
+            .field("emit_error_events", &self.emit_error_events)
+            .field("error_event_sample_rate", &self.error_event_sample_rate)
+            .field("emit_warning_events", &self.emit_warning_events);
+        debug
+            .field("attach_stacktraces", &self.attach_stacktraces)
+            .field("in_app_exclude", &self.in_app_exclude)
+            .field("extra_border_frames", &self.extra_border_frames)
+            .field("strip_ansi_escapes", &self.strip_ansi_escapes)
+            .field("bytes_preview_len", &self.bytes_preview_len)
+            .field("parse_json_fields", &self.parse_json_fields)
+            .field("event_type_field", &self.event_type_field)
+            .field("timestamp_field", &self.timestamp_field)
+            .field("field_routes", &self.field_routes)
+            .field("field_renames", &self.field_renames)
+            .field("private_field_prefix", &self.private_field_prefix)
+            .field("event_filter", &self.event_filter.as_ref().map(|_| ".."))
+            .field("span_filter", &self.span_filter.as_ref().map(|_| ".."))
+            .field("logger", &self.logger.as_ref().map(|_| ".."))
+            .field("span_contexts", &self.span_contexts)
+            .field("span_callsites", &self.span_callsites)
+            .field("transaction_name", &self.transaction_name)
+            .field("span_culprit", &self.span_culprit)
+            .field("instrument_errors", &self.instrument_errors)
+            .field("group_by_callsite", &self.group_by_callsite)
+            .field("fingerprint", &self.fingerprint.as_ref().map(|_| ".."))
+            .field("clock", &self.clock)
+            .field("resilient", &self.resilient)
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+impl TracingIntegrationOptions {
+    /// Sends events for tracing events at `level` and above, setting
+    /// [emit_error_events](Self::emit_error_events) and
+    /// [emit_warning_events](Self::emit_warning_events).
+    ///
+    /// Events are only sent for warnings and errors, so levels below
+    /// [Level::WARN] have the same effect as [Level::WARN].
+    pub fn event_level(mut self, level: Level) -> Self {
+        self.emit_error_events = true;
+        self.emit_warning_events = level >= Level::WARN;
+        self
+    }
+
+    /// Emits breadcrumbs for tracing events at `level` and above,
+    /// replacing the [filter](Self::filter).
+    pub fn breadcrumb_level(mut self, level: Level) -> Self {
+        self.filter = EnvFilter::default().add_directive(LevelFilter::from_level(level).into());
+        self
+    }
 }
 
 impl Default for TracingIntegrationOptions {
@@ -30,11 +577,49 @@ impl Default for TracingIntegrationOptions {
         Self {
             filter: EnvFilter::new("info"),
             emit_breadcrumbs: true,
+            breadcrumb_batch_size: None,
+            record_spans: true,
+            emit_http_breadcrumbs: false,
+            track_root_span_sessions: false,
+            slow_span_threshold: None,
+            abandoned_span_ttl: None,
+            attach_files: false,
+            max_attachment_bytes: 1024 * 1024,
+            max_event_bytes: None,
+            minimal_payload: false,
+            export_path: None,
+            install_global_subscriber: false,
+            capture_panics: false,
             emit_error_events: true,
+            error_event_sample_rate: SampleRates::default(),
             emit_warning_events: false,
             attach_stacktraces: true,
+            in_app_exclude: vec!["tracing_core::", "tracing_log::", "log::"],
+            extra_border_frames: vec![
+                "tracing_core::event::Event::dispatch",
+                "log::__private_api_log",
+            ],
             strip_ansi_escapes: false,
+            bytes_preview_len: 32,
+            parse_json_fields: JsonFieldParsing::Disabled,
             event_type_field: None,
+            timestamp_field: None,
+            field_routes: Vec::new(),
+            field_renames: HashMap::new(),
+            private_field_prefix: None,
+            event_filter: None,
+            span_filter: None,
+            logger: None,
+            span_contexts: false,
+            span_callsites: false,
+            transaction_name: TransactionName::Disabled,
+            span_culprit: SpanCulprit::Disabled,
+            instrument_errors: false,
+            group_by_callsite: false,
+            fingerprint: None,
+            clock: Arc::new(SystemClock),
+            resilient: true,
+            dry_run: DryRun::from_env(),
         }
     }
 }
@@ -43,24 +628,241 @@ impl Default for TracingIntegrationOptions {
 /// `tracing` framework.
 pub struct TracingIntegration {
     pub(crate) options: TracingIntegrationOptions,
+    pub(crate) watchdog: Arc<SpanWatchdog>,
+    pub(crate) export: Arc<EventExport>,
+    /// Replaces the [filter](TracingIntegrationOptions::filter) of the
+    /// options once set by [TracingIntegration::set_filter()].
+    filter_override: RwLock<Option<EnvFilter>>,
+    #[cfg(feature = "opentelemetry")]
+    sampled_trace_events_only: bool,
+    #[cfg(feature = "ctrlc")]
+    close_on_ctrlc: Option<std::time::Duration>,
+    #[cfg(feature = "log")]
+    log_bridge_level: Option<tracing_log::log::LevelFilter>,
 }
 
 impl TracingIntegration {
     /// Create a new [TracingIntegration] with the specified `options`.
     pub fn new(options: TracingIntegrationOptions) -> Self {
-        Self { options }
+        Self {
+            options,
+            watchdog: Arc::default(),
+            export: Arc::default(),
+            filter_override: RwLock::new(None),
+            #[cfg(feature = "opentelemetry")]
+            sampled_trace_events_only: false,
+            #[cfg(feature = "ctrlc")]
+            close_on_ctrlc: None,
+            #[cfg(feature = "log")]
+            log_bridge_level: None,
+        }
+    }
+
+    /// Only sends error and warning events when the OpenTelemetry trace
+    /// they occurred in is sampled, and otherwise only records them as
+    /// breadcrumbs, keeping the volume of events proportional to the
+    /// volume of traces. Events in traces whose sampling decision has
+    /// not been made yet are still sent.
+    ///
+    /// **Feature:** `opentelemetry` (*disabled by default*)
+    #[cfg(feature = "opentelemetry")]
+    pub fn sampled_trace_events_only(mut self) -> Self {
+        self.sampled_trace_events_only = true;
+        self
+    }
+
+    /// Installs a Ctrl-C handler when the integration is set up which
+    /// ends the release health session and closes the client,
+    /// delivering the pending events and waiting at most `timeout`,
+    /// before exiting the process (see
+    /// [close_on_ctrlc()](crate::close_on_ctrlc)). If a Ctrl-C handler
+    /// is already set, it is kept and a warning is printed to stderr.
+    ///
+    /// **Feature:** `ctrlc` (*disabled by default*)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let integration = sentry_tracing::TracingIntegration::default()
+    ///     .close_on_ctrlc(std::time::Duration::from_secs(2));
+    /// ```
+    #[cfg(feature = "ctrlc")]
+    pub fn close_on_ctrlc(mut self, timeout: std::time::Duration) -> Self {
+        self.close_on_ctrlc = Some(timeout);
+        self
+    }
+
+    /// Forwards records of the `log` crate at the specified `level` and
+    /// above to `tracing` when the integration is set up (see
+    /// [init_log_bridge_with_level()](crate::init_log_bridge_with_level)),
+    /// so that they are captured with the same converters and filters
+    /// as tracing events. If a global `log` logger is already set, it
+    /// is kept and a warning is printed to stderr.
+    ///
+    /// **Feature:** `log` (*disabled by default*)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tracing_log::log::LevelFilter;
+    ///
+    /// let integration = sentry_tracing::TracingIntegration::default().log_bridge(LevelFilter::Warn);
+    /// ```
+    #[cfg(feature = "log")]
+    pub fn log_bridge(mut self, level: tracing_log::log::LevelFilter) -> Self {
+        self.log_bridge_level = Some(level);
+        self
+    }
+
+    /// Replaces the [filter](TracingIntegrationOptions::filter) while
+    /// the integration is running, e.g. with directives fetched from a
+    /// configuration service, which can include per-module overrides
+    /// such as `info,noisy_crate=off`.
+    ///
+    /// See also [reload_filter()], for the integration bound to the
+    /// current hub.
+    pub fn set_filter(&self, filter: EnvFilter) {
+        *self
+            .filter_override
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(filter);
+    }
+
+    /// Checks if a breadcrumb should be emitted for the tracing event
+    /// with the specified `metadata`.
+    pub(crate) fn breadcrumb_enabled<S: Subscriber>(
+        &self,
+        metadata: &Metadata<'_>,
+        context: Context<'_, S>,
+    ) -> bool {
+        if !self.options.emit_breadcrumbs {
+            return false;
+        }
+        match &*self
+            .filter_override
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            Some(filter) => filter.enabled(metadata, context),
+            None => self.options.filter.enabled(metadata, context),
+        }
+    }
+
+    /// Checks if a breadcrumb should be emitted for the tracing event
+    /// with the specified `metadata`, which occurred outside of any
+    /// span (such as the events replayed from the
+    /// [pre_init_buffer](SentrySpanLayer::pre_init_buffer)), so that
+    /// only the directives of the filter which do not match spans apply.
+    pub(crate) fn breadcrumb_enabled_outside_spans(
+        &self,
+        metadata: &'static Metadata<'static>,
+    ) -> bool {
+        if !self.options.emit_breadcrumbs {
+            return false;
+        }
+        let filter = self
+            .filter_override
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let filter = filter.as_ref().unwrap_or(&self.options.filter);
+        Layer::<Registry>::register_callsite(filter, metadata).is_always()
+    }
+
+    /// Checks if the fields of the span with the specified `metadata`
+    /// need to be recorded.
+    pub(crate) fn record_span_fields(&self, metadata: &Metadata<'_>) -> bool {
+        has_feature_flag_fields(metadata)
+            || metadata.fields().field(ENVIRONMENT_FIELD).is_some()
+            || self.options.emit_http_breadcrumbs
+            || !self.options.field_routes.is_empty()
+            || self.options.capture_panics
+            || self.options.span_contexts
+            || self.options.slow_span_threshold.is_some()
+            || !matches!(self.options.span_culprit, SpanCulprit::Disabled)
+            || matches!(self.options.transaction_name, TransactionName::Field)
+    }
+
+    /// Checks if the tracing `event` passes the
+    /// [event_filter](TracingIntegrationOptions::event_filter).
+    pub(crate) fn event_enabled(&self, event: &tracing::Event<'_>) -> bool {
+        match &self.options.event_filter {
+            Some(event_filter) => event_filter(event, &visit_event_fields(event, &self.options)),
+            None => true,
+        }
+    }
+
+    /// Checks if the span with the specified `metadata` and `fields`
+    /// is not skipped with a [SKIP_FIELD], and passes the
+    /// [span_filter](TracingIntegrationOptions::span_filter).
+    pub(crate) fn span_enabled(
+        &self,
+        metadata: &'static Metadata<'static>,
+        fields: &SpanFields,
+    ) -> bool {
+        if fields.values.get(SKIP_FIELD) == Some(&serde_json::Value::Bool(true)) {
+            return false;
+        }
+        match &self.options.span_filter {
+            Some(span_filter) => span_filter(metadata, &fields.values),
+            None => true,
+        }
     }
 
     /// Checks if an issue should be created.
-    pub(crate) fn create_issue_for_event(&self, event: &tracing::Event<'_>) -> bool {
-        match event.metadata().level() {
-            &Level::WARN => self.options.emit_warning_events,
-            &Level::ERROR => self.options.emit_error_events,
+    ///
+    /// `sampled` is whether the trace the event occurred in is sampled,
+    /// if known.
+    #[cfg_attr(not(feature = "opentelemetry"), allow(unused_variables))]
+    pub(crate) fn create_issue_for_event(
+        &self,
+        event: &tracing::Event<'_>,
+        sampled: Option<bool>,
+    ) -> bool {
+        #[cfg(feature = "opentelemetry")]
+        if self.sampled_trace_events_only && sampled == Some(false) {
+            return false;
+        }
+
+        match *event.metadata().level() {
+            Level::WARN => self.options.emit_warning_events,
+            Level::ERROR => {
+                self.options.emit_error_events
+                    && self
+                        .options
+                        .error_event_sample_rate
+                        .sample(&event_target(event))
+            }
             _ => false,
         }
     }
 }
 
+/// Replaces the [filter](TracingIntegrationOptions::filter) of the
+/// [TracingIntegration] bound to the current hub, see
+/// [TracingIntegration::set_filter()].
+///
+/// Returns `false` if there is no such integration.
+///
+/// # Examples
+///
+/// ```
+/// let _sentry = sentry::init(
+///     sentry::ClientOptions::default().add_integration(sentry_tracing::TracingIntegration::default()),
+/// );
+///
+/// let filter = tracing_subscriber::EnvFilter::new("info,noisy_crate=off");
+/// sentry_tracing::reload_filter(filter);
+/// ```
+pub fn reload_filter(filter: EnvFilter) -> bool {
+    let mut filter = Some(filter);
+    sentry_core::with_integration(|integration: &TracingIntegration, _hub| {
+        if let Some(filter) = filter.take() {
+            integration.set_filter(filter);
+        }
+        true
+    })
+}
+
 impl Default for TracingIntegration {
     fn default() -> Self {
         Self::new(TracingIntegrationOptions::default())
@@ -73,11 +875,334 @@ impl Integration for TracingIntegration {
     }
 
     fn setup(&self, cfg: &mut ClientOptions) {
-        cfg.in_app_exclude.push("tracing_core::");
-        cfg.in_app_exclude.push("tracing_log::");
-        cfg.in_app_exclude.push("log::");
+        cfg.in_app_exclude
+            .extend(self.options.in_app_exclude.iter().copied());
         cfg.extra_border_frames
-            .push("tracing_core::event::Event::dispatch");
-        cfg.extra_border_frames.push("log::__private_api_log");
+            .extend(self.options.extra_border_frames.iter().copied());
+
+        if !CAPTURE {
+            return;
+        }
+
+        if self.options.capture_panics {
+            install_panic_hook();
+        }
+
+        if let Some(path) = &self.options.export_path {
+            if let Err(error) = self.export.open(path) {
+                eprintln!(
+                    "sentry-tracing could not open {} to export events: {}",
+                    path.display(),
+                    error
+                );
+            }
+        }
+
+        match (self.options.slow_span_threshold, self.options.abandoned_span_ttl) {
+            (None, Some(_)) => eprintln!(
+                "sentry-tracing ignores the abandoned_span_ttl, as no slow_span_threshold is set"
+            ),
+            (Some(threshold), Some(ttl)) if ttl < threshold => eprintln!(
+                "sentry-tracing releases slow spans once reported, as the abandoned_span_ttl ({:?}) is shorter than the slow_span_threshold ({:?})",
+                ttl, threshold
+            ),
+            _ => {}
+        }
+
+        if let Some(threshold) = self.options.slow_span_threshold {
+            let ttl = self.options.abandoned_span_ttl;
+            let clock = self.options.clock.clone();
+            if let Err(error) = self.watchdog.start(
+                threshold,
+                ttl,
+                clock,
+                self.options.dry_run,
+                self.export.clone(),
+            ) {
+                eprintln!(
+                    "sentry-tracing could not start the slow span watchdog: {}",
+                    error
+                );
+            }
+        }
+
+        #[cfg(feature = "ctrlc")]
+        if let Some(timeout) = self.close_on_ctrlc {
+            if let Err(error) = crate::close_on_ctrlc(timeout) {
+                eprintln!(
+                    "sentry-tracing could not install the Ctrl-C handler: {}",
+                    error
+                );
+            }
+        }
+
+        #[cfg(feature = "log")]
+        if let Some(level) = self.log_bridge_level {
+            if let Err(error) = crate::init_log_bridge_with_level(level) {
+                eprintln!("sentry-tracing could not install the log bridge: {}", error);
+            }
+        }
+
+        if self.options.install_global_subscriber {
+            let subscriber = tracing_subscriber::registry().with(SentrySpanLayer::default());
+            if let Err(error) = tracing::subscriber::set_global_default(subscriber) {
+                eprintln!(
+                    "sentry-tracing could not install the global default subscriber: {}",
+                    error
+                );
+            }
+        }
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if !take_own_event_mark(&mut event) {
+            add_queued_breadcrumbs(&mut event, options);
+            return Some(event);
+        }
+        if !CAPTURE {
+            return Some(event);
+        }
+        if self.options.minimal_payload {
+            minimize_payload(&mut event);
+        }
+        if let Some(max_event_bytes) = self.options.max_event_bytes {
+            enforce_size_budget(&mut event, max_event_bytes);
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry_core::{ClientOptions, Hub};
+
+    use tracing::Level;
+    use tracing_subscriber::EnvFilter;
+
+    use super::{TracingIntegration, TracingIntegrationOptions};
+    use crate::layer::CAPTURE;
+    use crate::test::{with_captured_client, with_captured_options};
+    use crate::SampleRates;
+
+    fn error_sampling_options(rates: &str) -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            error_event_sample_rate: rates.parse().unwrap(),
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    #[test]
+    fn error_events_are_sampled_by_target() {
+        let options = error_sampling_options("0.0,important=1.0");
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(target: "chatty", "Dropped");
+            tracing::error!(target: "important::db", "Sent");
+            tracing::warn!(target: "chatty", "Not an error event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("message=Sent")
+        );
+        // Dropped error events are still recorded as breadcrumbs.
+        assert_eq!(captured.breadcrumbs.len(), 3);
+    }
+
+    #[test]
+    fn bridged_log_records_are_sampled_by_their_original_target() {
+        let options = TracingIntegrationOptions {
+            error_event_sample_rate: SampleRates::new(1.0).with_target("chatty", 0.0),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            tracing::event!(target: "log", tracing::Level::ERROR, log.target = "chatty::db", "Dropped");
+            tracing::event!(target: "log", tracing::Level::ERROR, log.target = "quiet", "Sent");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.events[0].logger.as_deref(), Some("quiet"));
+    }
+
+    #[test]
+    fn the_export_file_is_only_opened_with_capture() {
+        let path = std::env::temp_dir().join("sentry-tracing-no-capture.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let options = ClientOptions::default().add_integration(TracingIntegration::new(
+            TracingIntegrationOptions {
+                export_path: Some(path.clone()),
+                ..TracingIntegrationOptions::default()
+            },
+        ));
+        let _hub = Hub::new(
+            Some(std::sync::Arc::new(options.into())),
+            Default::default(),
+        );
+
+        assert_eq!(path.exists(), cfg!(feature = "capture"));
+    }
+
+    fn messages(breadcrumbs: &[sentry_core::Breadcrumb]) -> Vec<String> {
+        breadcrumbs
+            .iter()
+            .filter_map(|breadcrumb| breadcrumb.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn event_level_sets_the_lowest_level_of_events() {
+        let emit = |level| {
+            let options = TracingIntegrationOptions::default().event_level(level);
+            with_captured_options(options, |_| {
+                tracing::error!("Error");
+                tracing::warn!("Warning");
+                tracing::info!("Info");
+            })
+            .events
+            .len()
+        };
+
+        assert_eq!(emit(Level::ERROR), 1);
+        assert_eq!(emit(Level::WARN), 2);
+        assert_eq!(emit(Level::INFO), 2);
+    }
+
+    #[test]
+    fn breadcrumb_level_replaces_the_filter() {
+        let emit = |level| {
+            let options = TracingIntegrationOptions::default().breadcrumb_level(level);
+            let captured = with_captured_options(options, |_| {
+                tracing::warn!("Warning");
+                tracing::info!("Info");
+                tracing::debug!("Debug");
+            });
+            messages(&captured.breadcrumbs)
+        };
+
+        assert_eq!(emit(Level::WARN), ["message=Warning"]);
+        assert_eq!(emit(Level::INFO), ["message=Warning", "message=Info"]);
+    }
+
+    #[test]
+    fn event_filter_drops_events_by_field_value() {
+        let options = TracingIntegrationOptions {
+            event_filter: Some(Box::new(|_, fields| {
+                fields.json_values.get("user") != Some(&"bot".into())
+            })),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            tracing::error!(user = "bot", "Dropped");
+            tracing::info!(user = "bot", "Dropped");
+            tracing::error!(user = "alice", "Kept");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            messages(&captured.breadcrumbs),
+            ["message=Kept\nuser=alice"]
+        );
+    }
+
+    #[test]
+    fn the_filter_can_be_reloaded_at_runtime() {
+        let captured = with_captured_options(TracingIntegrationOptions::default(), |_| {
+            tracing::info!("Before");
+            assert!(super::reload_filter(EnvFilter::new("warn")));
+            tracing::info!("Filtered out");
+            tracing::warn!("After");
+        });
+
+        assert_eq!(
+            messages(&captured.breadcrumbs),
+            ["message=Before", "message=After"]
+        );
+    }
+
+    #[test]
+    fn reloading_the_filter_requires_an_integration() {
+        let hub = std::sync::Arc::new(Hub::new(None, Default::default()));
+        Hub::run(hub, || {
+            assert!(!super::reload_filter(EnvFilter::new("warn")))
+        });
+    }
+
+    #[test]
+    fn own_events_keep_the_sdk_info_of_the_client() {
+        let captured = with_captured_options(TracingIntegrationOptions::default(), |_| {
+            tracing::error!("Failed");
+        });
+
+        let event = &captured.events[0];
+        assert!(event.extra.is_empty());
+        let sdk = event.sdk.as_ref().expect("the client sets the sdk info");
+        assert_eq!(sdk.name, "sentry.rust");
+        assert!(sdk.integrations.iter().any(|name| name == "tracing"));
+    }
+
+    #[test]
+    fn events_of_others_include_the_queued_breadcrumbs() {
+        let options = TracingIntegrationOptions {
+            breadcrumb_batch_size: Some(10),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |sink| {
+            tracing::info!("Queued");
+            sink.hub()
+                .capture_message("Captured by others", sentry_core::Level::Error);
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            messages(&captured.events[0].breadcrumbs),
+            ["message=Queued"]
+        );
+        // The breadcrumbs are still added to the scope later on.
+        assert_eq!(messages(&captured.breadcrumbs), ["message=Queued"]);
+    }
+
+    #[test]
+    fn setup_adds_the_in_app_exclude_and_border_frames() {
+        let options = ClientOptions {
+            in_app_exclude: vec!["app::vendored::"],
+            ..ClientOptions::default()
+        }
+        .add_integration(TracingIntegration::new(TracingIntegrationOptions {
+            in_app_exclude: vec!["tracing_core::", "my_logging::"],
+            extra_border_frames: vec!["my_logging::log"],
+            ..TracingIntegrationOptions::default()
+        }));
+        let client = sentry_core::Client::from(options);
+
+        let options = client.options();
+        assert_eq!(
+            options.in_app_exclude,
+            ["app::vendored::", "tracing_core::", "my_logging::"]
+        );
+        assert_eq!(options.extra_border_frames, ["my_logging::log"]);
+    }
+
+    #[test]
+    fn setup_installs_the_global_subscriber() {
+        let integration = TracingIntegration::new(TracingIntegrationOptions {
+            install_global_subscriber: true,
+            ..TracingIntegrationOptions::default()
+        });
+        let subscriber = tracing_subscriber::registry();
+        let captured =
+            with_captured_client(ClientOptions::default(), integration, subscriber, |sink| {
+                let hub = sink.hub().clone();
+                // Other threads only see the global default subscriber.
+                std::thread::spawn(move || Hub::run(hub, || tracing::error!("Global")))
+                    .join()
+                    .unwrap();
+            });
+
+        assert_eq!(tracing::dispatcher::has_been_set(), CAPTURE);
+        assert_eq!(captured.events.len(), usize::from(CAPTURE));
     }
 }
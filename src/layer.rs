@@ -1,10 +1,45 @@
-use crate::{breadcrumb_from_event, converters::convert_tracing_event, TracingIntegration};
+use crate::attachments::{attachment_from_event, capture_event_with_attachment};
+use crate::breadcrumb_batch::{flush_breadcrumbs, queue_breadcrumb};
+use crate::converters::{
+    add_feature_flags, convert_tracing_event, http_breadcrumb_from_span, instrument_error,
+    mark_own_event, split_error_type, SpanFields,
+};
+use crate::pre_init::PreInitBuffer;
+use crate::watchdog::WatchedSpanId;
+use crate::{
+    breadcrumb_from_event, BufferOverflow, SpanCulprit, TracingIntegration, TransactionName,
+};
 
-use sentry_core::Hub;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use sentry_core::{Breadcrumb, Hub};
+use serde::Serialize;
 use tracing::{span, Event, Subscriber};
-use tracing_subscriber::{layer::Context, Layer};
+use tracing_subscriber::{
+    layer::Context,
+    registry::{LookupSpan, SpanRef},
+    Layer,
+};
+
+/// Whether anything is captured. Without the `capture` feature, the
+/// hooks of the layers return immediately, and the integration sets
+/// nothing up.
+pub(crate) const CAPTURE: bool = cfg!(feature = "capture");
 
 /// Provides a dispatching logger.
+///
+/// Captures tracing events as Sentry events and breadcrumbs into the
+/// current (thread-local) [Hub], with any subscriber. Events are not
+/// correlated with the spans they occur in, use a [SentrySpanLayer]
+/// for that with subscribers built on the `Registry`, to capture into
+/// a specific [Hub] (see [SentrySpanLayer::with_hub()]), or to disable
+/// capturing at runtime (see [SentrySpanLayer::set_enabled()]).
+///
+/// The [TracingIntegration] is looked up whenever the layer is
+/// notified, so the layer may be installed before `sentry::init` is
+/// called, and picks up clients which are bound later on.
 #[derive(Default)]
 pub struct SentryLayer;
 
@@ -14,28 +49,1360 @@ impl<S: Subscriber> Layer<S> for SentryLayer {
 
     /// Notifies this layer that an event has occurred.
     fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
-        let recorded =
-            sentry_core::with_integration(|integration: &TracingIntegration, hub: &Hub| {
-                if integration.create_issue_for_event(event) {
-                    hub.capture_event(convert_tracing_event(event, &integration.options));
+        if !CAPTURE {
+            return;
+        }
+        if !on_event(None, event, context, None, |_, _| {}) {
+            warn_not_recorded();
+        }
+    }
+}
+
+/// A [SentryLayer] which also records spans, for subscribers which
+/// implement [LookupSpan] (i.e. which are built on the `Registry`).
+///
+/// Events are correlated with the spans they occur in, which the
+/// options relying on span data (such as
+/// [span_contexts](crate::TracingIntegrationOptions::span_contexts) or
+/// [track_root_span_sessions](crate::TracingIntegrationOptions::track_root_span_sessions))
+/// require.
+///
+/// By default, the layer captures into the current (thread-local)
+/// [Hub]. Use [SentrySpanLayer::with_hub] to bind it to a specific one.
+///
+/// The [TracingIntegration] is looked up whenever the layer is
+/// notified, so the layer may be installed before `sentry::init` is
+/// called, and picks up clients which are bound later on. Events
+/// which occur while no client with the integration is bound are
+/// dropped (unless they are buffered, see
+/// [SentrySpanLayer::pre_init_buffer()]), and spans created in the
+/// meantime only carry the fields recorded after one is bound.
+///
+/// Events which occur within a span with a `sentry.environment` field
+/// (or within its descendants) are reported with its value as their
+/// environment instead of the one of the client, e.g. for processes
+/// which serve several environments:
+///
+/// ```
+/// let span = tracing::info_span!("request", sentry.environment = "staging");
+/// ```
+///
+/// Once the subscriber is installed, the layer can be controlled with
+/// [SentrySpanLayer::with_current()].
+#[derive(Default)]
+pub struct SentrySpanLayer {
+    hub: Option<Arc<Hub>>,
+    disabled: AtomicBool,
+    pre_init_buffer: Option<PreInitBuffer>,
+}
+
+impl SentrySpanLayer {
+    /// Creates a layer which captures into the current [Hub].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a layer which captures into the given `hub`, regardless
+    /// of which [Hub] is current on the thread emitting the spans and
+    /// events.
+    ///
+    /// The [TracingIntegration] must be added to the client bound to
+    /// the `hub`, otherwise nothing is captured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use tracing_subscriber::prelude::*;
+    ///
+    /// let client = sentry::Client::from(
+    ///     sentry::ClientOptions::default()
+    ///         .add_integration(sentry_tracing::TracingIntegration::default()),
+    /// );
+    /// let hub = Arc::new(sentry::Hub::new(
+    ///     Some(Arc::new(client)),
+    ///     Arc::new(sentry::Scope::default()),
+    /// ));
+    /// let subscriber = tracing_subscriber::registry()
+    ///     .with(sentry_tracing::SentrySpanLayer::with_hub(hub));
+    /// ```
+    pub fn with_hub(hub: Arc<Hub>) -> Self {
+        Self {
+            hub: Some(hub),
+            ..Self::default()
+        }
+    }
+
+    /// Buffers up to `capacity` events which occur while no client with
+    /// the [TracingIntegration] is bound (e.g. before `sentry::init` is
+    /// called), and replays them once one is, with the next event or
+    /// when the client is closed with [close()](crate::close). Once
+    /// the buffer is full, events are dropped according to `overflow`.
+    ///
+    /// Replayed events keep the time they occurred at, but are not
+    /// correlated with the spans they occurred in, as spans are not
+    /// buffered. They are filtered as if they occurred outside of any
+    /// span.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentry_tracing::{BufferOverflow, SentrySpanLayer};
+    /// use tracing_subscriber::prelude::*;
+    ///
+    /// let subscriber = tracing_subscriber::registry()
+    ///     .with(SentrySpanLayer::new().pre_init_buffer(100, BufferOverflow::DropOldest));
+    /// ```
+    pub fn pre_init_buffer(mut self, capacity: usize, overflow: BufferOverflow) -> Self {
+        self.pre_init_buffer = Some(PreInitBuffer::new(capacity, overflow));
+        self
+    }
+
+    /// Replays the events buffered before a client with the
+    /// [TracingIntegration] was bound, if it is bound by now.
+    pub(crate) fn replay_pre_init_buffer(&self) {
+        let buffer = match &self.pre_init_buffer {
+            Some(buffer) if self.capturing() => buffer,
+            _ => return,
+        };
+        buffer.replay(|buffered, timestamp| {
+            let metadata = buffered.metadata();
+            record_event(
+                self.hub.as_ref(),
+                buffered,
+                None,
+                Some(timestamp),
+                |integration| integration.breadcrumb_enabled_outside_spans(metadata),
+                |_, _| {},
+            )
+        });
+    }
+
+    /// Calls `f` with the layer of the current default subscriber, if it
+    /// contains a [SentrySpanLayer], so that the layer can be controlled
+    /// after the subscriber was installed.
+    ///
+    /// The layer is found in subscribers composed with
+    /// `SubscriberExt::with`, otherwise `None` is returned without
+    /// calling `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Stop capturing, e.g. while running a noisy batch job.
+    /// sentry_tracing::SentrySpanLayer::with_current(|layer| layer.set_enabled(false));
+    /// ```
+    pub fn with_current<F, R>(f: F) -> Option<R>
+    where
+        F: FnOnce(&SentrySpanLayer) -> R,
+    {
+        let mut f = Some(f);
+        tracing::dispatcher::get_default(|dispatch| {
+            let layer = dispatch.downcast_ref::<SentrySpanLayer>()?;
+            f.take().map(|f| f(layer))
+        })
+    }
+
+    /// Enables or disables the layer. While it is disabled, nothing is
+    /// captured, as if the integration was not enabled, which can be
+    /// used as a kill switch at runtime. Layers are enabled when
+    /// created.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.disabled.store(!enabled, Ordering::Relaxed);
+    }
+
+    /// Whether the layer is enabled, see [SentrySpanLayer::set_enabled()].
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the layer captures anything.
+    fn capturing(&self) -> bool {
+        CAPTURE && self.is_enabled()
+    }
+
+    /// Calls `f` with the [TracingIntegration] and the [Hub] this layer
+    /// captures into, if the integration is enabled on its client.
+    fn with_integration<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&TracingIntegration, &Hub) -> R,
+        R: Default,
+    {
+        with_integration(self.hub.as_ref(), f)
+    }
+}
+
+/// Calls `f` with the [TracingIntegration] and the `hub`, or the
+/// current [Hub] if it is `None`.
+fn with_integration<F, R>(hub: Option<&Arc<Hub>>, f: F) -> R
+where
+    F: FnOnce(&TracingIntegration, &Hub) -> R,
+    R: Default,
+{
+    match hub {
+        Some(hub) => hub.with_integration(|integration: &TracingIntegration| f(integration, hub)),
+        None => sentry_core::with_integration(f),
+    }
+}
+
+/// The release health session started for a root span, stored in
+/// its extensions.
+struct RootSpanSession {
+    hub: Arc<Hub>,
+}
+
+/// Pretty prints a `value` which would be sent to Sentry to stderr.
+pub(crate) fn print_dry_run<T: Serialize>(kind: &str, value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => eprintln!("sentry-tracing {}: {}", kind, json),
+        Err(error) => eprintln!("sentry-tracing {} could not be printed: {}", kind, error),
+    }
+}
+
+/// Adds a `breadcrumb` to the scope of the `hub`, taking into account
+/// the dry run mode and the
+/// [breadcrumb_batch_size](crate::TracingIntegrationOptions::breadcrumb_batch_size)
+/// of the `integration`.
+///
+/// `bound_hub` is the [Hub] the layer is bound to, if any, which is
+/// the `hub` to queue batched breadcrumbs for.
+fn add_breadcrumb(
+    integration: &TracingIntegration,
+    bound_hub: Option<&Arc<Hub>>,
+    hub: &Hub,
+    breadcrumb: Breadcrumb,
+) {
+    let dry_run = integration.options.dry_run;
+    if dry_run.print() {
+        print_dry_run("breadcrumb", &breadcrumb);
+    }
+    if dry_run.send() {
+        match integration.options.breadcrumb_batch_size {
+            Some(batch_size) => {
+                let hub = bound_hub.cloned().unwrap_or_else(Hub::current);
+                queue_breadcrumb(hub, breadcrumb, batch_size);
+            }
+            None => hub.add_breadcrumb(breadcrumb),
+        }
+    }
+}
+
+/// Whether it was already reported that a span was not found by
+/// [expect_span()].
+static WARNED_SPAN_NOT_FOUND: AtomicBool = AtomicBool::new(false);
+
+/// Looks up the span with the specified `id`, which should always
+/// exist.
+///
+/// If it does not, this panics unless the integration is
+/// [resilient](crate::TracingIntegrationOptions::resilient), in which
+/// case a warning is printed (once) and `None` is returned.
+fn expect_span<'c, S>(
+    ctx: &'c Context<'_, S>,
+    id: &span::Id,
+    integration: &TracingIntegration,
+) -> Option<SpanRef<'c, S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let span = ctx.span(id);
+    if span.is_none() {
+        if !integration.options.resilient {
+            panic!("Span not found, this is a bug");
+        }
+        if !WARNED_SPAN_NOT_FOUND.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "sentry-tracing: span {:?} not found, this is a bug. This is only reported once.",
+                id
+            );
+        }
+    }
+    span
+}
+
+impl<S> Layer<S> for SentrySpanLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    /// Notifies this layer that a new span was constructed with the
+    /// given `Attributes` and `Id`.
+    fn new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if !self.capturing() {
+            return;
+        }
+        self.with_integration(|integration, _hub| {
+            if !integration.options.record_spans {
+                return;
+            }
+            let span = match expect_span(&ctx, id, integration) {
+                Some(span) => span,
+                None => return,
+            };
+            let is_root = span.parent().is_none();
+            let mut extensions = span.extensions_mut();
+
+            let mut fields = SpanFields::default();
+            if integration.record_span_fields(attrs.metadata()) && !attrs.is_empty() {
+                fields.record(attrs, &integration.options);
+            }
+
+            if integration.options.slow_span_threshold.is_some()
+                && integration.span_enabled(span.metadata(), &fields)
+            {
+                let hub = self.hub.clone().unwrap_or_else(Hub::current);
+                extensions.insert(integration.watchdog.watch(
+                    span.metadata(),
+                    fields.values.clone(),
+                    integration.options.clock.now(),
+                    hub,
+                ));
+            }
+
+            if !fields.is_empty() {
+                extensions.insert(fields);
+            }
+
+            if integration.options.track_root_span_sessions && is_root {
+                let hub = self.hub.clone().unwrap_or_else(Hub::current);
+                hub.start_session();
+                extensions.insert(RootSpanSession { hub });
+            }
+        });
+    }
+
+    /// Notifies this layer that a span with the given `Id` recorded
+    /// the given `values`.
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if !self.capturing() {
+            return;
+        }
+        self.with_integration(|integration, _hub| {
+            if !integration.options.record_spans {
+                return;
+            }
+            let span = match expect_span(&ctx, id, integration) {
+                Some(span) => span,
+                None => return,
+            };
+            let mut extensions = span.extensions_mut();
+            match extensions.get_mut::<SpanFields>() {
+                Some(fields) => fields.record(values, &integration.options),
+                // The fields are only stored once the span has any.
+                None if integration.record_span_fields(span.metadata()) => {
+                    let mut fields = SpanFields::default();
+                    fields.record(values, &integration.options);
+                    if !fields.is_empty() {
+                        extensions.insert(fields);
+                    }
                 }
+                None => {}
+            }
 
-                if integration.options.emit_breadcrumbs
-                    && integration
-                        .options
-                        .filter
-                        .enabled(event.metadata(), context)
+            if integration.options.slow_span_threshold.is_some() {
+                let values = extensions
+                    .get_mut::<SpanFields>()
+                    .map(|fields| fields.values.clone());
+                if let (Some(watched), Some(values)) =
+                    (extensions.get_mut::<WatchedSpanId>(), values)
                 {
-                    sentry_core::add_breadcrumb(|| {
-                        breadcrumb_from_event(event, &integration.options)
-                    });
+                    watched.update_fields(values);
                 }
+            }
+        });
+    }
 
-                true
-            });
+    /// Notifies this layer that a span with the given ID was entered.
+    fn on_enter(&self, _id: &span::Id, _ctx: Context<'_, S>) {}
+
+    /// Notifies this layer that the span with the given ID has been
+    /// closed.
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        // The session is ended even if the layer or the integration is
+        // no longer enabled, e.g. because the client was unbound in the
+        // meantime.
+        if let Some(span) = ctx.span(&id) {
+            if let Some(session) = span.extensions_mut().remove::<RootSpanSession>() {
+                session.hub.end_session();
+            }
+        }
+
+        if !self.capturing() {
+            return;
+        }
+        self.with_integration(|integration, hub| {
+            if !integration.options.record_spans {
+                return;
+            }
+            let span = match expect_span(&ctx, &id, integration) {
+                Some(span) => span,
+                None => return,
+            };
+            if integration.options.emit_http_breadcrumbs
+                && integration.breadcrumb_enabled(span.metadata(), ctx.clone())
+            {
+                let breadcrumb = span
+                    .extensions()
+                    .get::<SpanFields>()
+                    .filter(|fields| integration.span_enabled(span.metadata(), fields))
+                    .and_then(|fields| http_breadcrumb_from_span(fields, &integration.options));
+                if let Some(breadcrumb) = breadcrumb {
+                    add_breadcrumb(integration, self.hub.as_ref(), hub, breadcrumb);
+                }
+            }
+        });
+    }
+
+    /// Notifies this layer that an event has occurred.
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        if !self.capturing() {
+            return;
+        }
+        #[cfg(feature = "opentelemetry")]
+        let sampled = context
+            .event_span(event)
+            .and_then(|span| crate::opentelemetry::is_sampled(&span));
+        #[cfg(not(feature = "opentelemetry"))]
+        let sampled = None;
 
+        // Replay the buffered events first, to keep them in order.
+        self.replay_pre_init_buffer();
+
+        let span_context = context.clone();
+        let recorded = on_event(
+            self.hub.as_ref(),
+            event,
+            context,
+            sampled,
+            |sentry_event, integration| {
+                enrich_event_from_span(sentry_event, event, &span_context, integration)
+            },
+        );
         if !recorded {
-            eprintln!("Tracing event was not recorded by sentry because it has no `TracingIntegration` applied.")
+            match &self.pre_init_buffer {
+                Some(buffer) => buffer.push(event),
+                None => warn_not_recorded(),
+            }
+        }
+    }
+}
+
+/// Adds information about the span which the tracing `event` occurred
+/// in to the `sentry_event`.
+fn enrich_event_from_span<S>(
+    sentry_event: &mut sentry_core::protocol::Event<'static>,
+    event: &Event<'_>,
+    context: &Context<'_, S>,
+    integration: &TracingIntegration,
+) where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if !integration.options.record_spans {
+        return;
+    }
+    let span = match context.event_span(event) {
+        Some(span) => span,
+        None => return,
+    };
+
+    #[cfg(feature = "opentelemetry")]
+    if let Some(trace_context) = crate::opentelemetry::trace_context(&span) {
+        sentry_event.contexts.insert(
+            "trace".into(),
+            sentry_core::protocol::Context::Trace(Box::new(trace_context)),
+        );
+    }
+
+    if integration.options.instrument_errors {
+        if let Some(error) = instrument_error(event, span.metadata()) {
+            apply_instrument_error(sentry_event, &error, &span);
+        }
+    }
+
+    if let Some(transaction) = transaction_name(&integration.options.transaction_name, &span) {
+        sentry_event.transaction = Some(transaction);
+    }
+
+    if let Some(environment) = span_environment(integration, &span) {
+        sentry_event.environment = Some(environment.into());
+    }
+
+    if let Some(culprit) = span_culprit(integration, &span) {
+        sentry_event.culprit = Some(culprit);
+    }
+
+    for span in span.scope() {
+        let extensions = span.extensions();
+        if let Some(fields) = extensions
+            .get::<SpanFields>()
+            .filter(|fields| integration.span_enabled(span.metadata(), fields))
+        {
+            add_feature_flags(sentry_event, &fields.feature_flags);
+            fields.add_to_event(sentry_event);
+        }
+    }
+
+    if integration.options.span_contexts {
+        let no_fields = SpanFields::default();
+        for span in span.scope().from_root() {
+            let extensions = span.extensions();
+            // Spans without fields only have a context for their
+            // callsite.
+            let fields = match extensions.get::<SpanFields>() {
+                Some(fields) => fields,
+                None if integration.options.span_callsites => &no_fields,
+                None => continue,
+            };
+            if !integration.span_enabled(span.metadata(), fields) {
+                continue;
+            }
+            let mut context: sentry_core::protocol::Map<_, _> =
+                fields.values.clone().into_iter().collect();
+            if integration.options.span_callsites {
+                add_callsite(&mut context, span.metadata());
+            }
+            sentry_event.contexts.insert(
+                format!("span.{}", span.name()),
+                sentry_core::protocol::Context::Other(context),
+            );
+        }
+    }
+}
+
+/// Adds the source file, line and target of the callsite described by
+/// the `metadata` to the `context` of a span.
+fn add_callsite(
+    context: &mut sentry_core::protocol::Map<String, serde_json::Value>,
+    metadata: &tracing::Metadata<'_>,
+) {
+    if let Some(file) = metadata.file() {
+        context.insert("code.filepath".into(), file.into());
+    }
+    if let Some(line) = metadata.line() {
+        context.insert("code.lineno".into(), line.into());
+    }
+    context.insert("target".into(), metadata.target().into());
+}
+
+/// Determines the transaction name for an event which occurred in the
+/// `span`, according to the `strategy`.
+fn transaction_name<S>(strategy: &TransactionName, span: &SpanRef<'_, S>) -> Option<String>
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    match strategy {
+        TransactionName::Disabled => None,
+        TransactionName::RootSpan => span
+            .scope()
+            .from_root()
+            .next()
+            .map(|root| root.name().to_owned()),
+        #[cfg(feature = "opentelemetry")]
+        TransactionName::NearestTraced => span
+            .scope()
+            .find(|span| crate::opentelemetry::trace_context(span).is_some())
+            .map(|span| span.name().to_owned()),
+        #[cfg(not(feature = "opentelemetry"))]
+        TransactionName::NearestTraced => None,
+        TransactionName::NearestMatching(predicate) => span
+            .scope()
+            .find(|span| predicate(span.metadata()))
+            .map(|span| span.name().to_owned()),
+        TransactionName::Field => span.scope().find_map(|span| {
+            let extensions = span.extensions();
+            let value = extensions
+                .get::<SpanFields>()?
+                .values
+                .get(TransactionName::FIELD)?;
+            Some(match value {
+                serde_json::Value::String(name) => name.clone(),
+                value => value.to_string(),
+            })
+        }),
+        TransactionName::Callback(callback) => {
+            let metadata = span.scope().map(|span| span.metadata()).collect::<Vec<_>>();
+            callback(&metadata)
+        }
+    }
+}
+
+/// The value of the `sentry.environment` field of the `span` or the
+/// nearest of its ancestors which has one, ignoring the spans which are
+/// not [enabled](TracingIntegration::span_enabled).
+fn span_environment<S>(integration: &TracingIntegration, span: &SpanRef<'_, S>) -> Option<String>
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    span.scope().find_map(|span| {
+        let extensions = span.extensions();
+        extensions
+            .get::<SpanFields>()
+            .filter(|fields| integration.span_enabled(span.metadata(), fields))?
+            .environment
+            .clone()
+    })
+}
+
+/// Derives the culprit for an event which occurred in the `span`,
+/// according to the [span_culprit](crate::TracingIntegrationOptions::span_culprit)
+/// option.
+fn span_culprit<S>(integration: &TracingIntegration, span: &SpanRef<'_, S>) -> Option<String>
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    let extensions = span.extensions();
+    let no_fields = SpanFields::default();
+    let fields = extensions.get::<SpanFields>().unwrap_or(&no_fields);
+    if !integration.span_enabled(span.metadata(), fields) {
+        return None;
+    }
+    match &integration.options.span_culprit {
+        SpanCulprit::Disabled => None,
+        SpanCulprit::Span => Some(match fields.values.get(SpanCulprit::DESCRIPTION_FIELD) {
+            Some(serde_json::Value::String(description)) => {
+                format!("{}: {}", span.name(), description)
+            }
+            Some(description) => format!("{}: {}", span.name(), description),
+            None => span.name().to_owned(),
+        }),
+        SpanCulprit::Callback(callback) => callback(span.metadata(), &fields.values),
+    }
+}
+
+/// Attributes an error returned by a function annotated with
+/// `#[instrument(err)]` to the function (`span`), rather than to the
+/// event emitted for it by the generated code.
+fn apply_instrument_error<S>(
+    sentry_event: &mut sentry_core::protocol::Event<'static>,
+    error: &str,
+    span: &SpanRef<'_, S>,
+) where
+    S: for<'a> LookupSpan<'a>,
+{
+    let metadata = span.metadata();
+    let function = match metadata.module_path() {
+        Some(module_path) => format!("{}::{}", module_path, span.name()),
+        None => span.name().to_owned(),
+    };
+
+    if let Some(exception) = sentry_event.exception.values.first_mut() {
+        let (ty, value) = match split_error_type(error) {
+            Some((ty, value)) => (ty.to_owned(), value.to_owned()),
+            None => (
+                format!("[{}] {} error", metadata.target(), span.name()),
+                error.to_owned(),
+            ),
+        };
+        exception.ty = ty;
+        exception.value = Some(value);
+        exception.module = metadata.module_path().map(|p| p.to_owned());
+    }
+    sentry_event.culprit = Some(function);
+}
+
+/// Whether it was already reported that an event was not recorded
+/// because the [TracingIntegration] is not (yet) enabled.
+static WARNED_NOT_RECORDED: AtomicBool = AtomicBool::new(false);
+
+/// Captures the tracing `event`, which occurred in the `context`, as
+/// described by [record_event()].
+fn on_event<S, F>(
+    bound_hub: Option<&Arc<Hub>>,
+    event: &Event<'_>,
+    context: Context<'_, S>,
+    sampled: Option<bool>,
+    enrich_event: F,
+) -> bool
+where
+    S: Subscriber,
+    F: FnOnce(&mut sentry_core::protocol::Event<'static>, &TracingIntegration),
+{
+    let metadata = event.metadata();
+    record_event(
+        bound_hub,
+        event,
+        sampled,
+        None,
+        |integration| integration.breadcrumb_enabled(metadata, context),
+        enrich_event,
+    )
+}
+
+/// Captures the tracing `event` as a Sentry event and/or breadcrumb
+/// into the `bound_hub` (or the current [Hub] if `None`), according to the
+/// options of the [TracingIntegration].
+///
+/// `sampled` is whether the trace the event occurred in is sampled, if
+/// known. `timestamp` is when the event occurred, if it is captured
+/// later on. `breadcrumb_enabled` checks the breadcrumb filter of the
+/// integration for the event. `enrich_event` is called with each
+/// Sentry event and the integration before the event is captured.
+///
+/// Returns whether the event was recorded, which it is not if the
+/// integration is not enabled.
+fn record_event<B, F>(
+    bound_hub: Option<&Arc<Hub>>,
+    event: &Event<'_>,
+    sampled: Option<bool>,
+    timestamp: Option<SystemTime>,
+    breadcrumb_enabled: B,
+    enrich_event: F,
+) -> bool
+where
+    B: FnOnce(&TracingIntegration) -> bool,
+    F: FnOnce(&mut sentry_core::protocol::Event<'static>, &TracingIntegration),
+{
+    with_integration(bound_hub, |integration, hub| {
+        let create_issue = integration.create_issue_for_event(event, sampled);
+        let emit_breadcrumb = breadcrumb_enabled(integration);
+        // The fields of the event are only visited for the event filter
+        // if there is anything to capture.
+        if !(create_issue || emit_breadcrumb) || !integration.event_enabled(event) {
+            return true;
+        }
+
+        let dry_run = integration.options.dry_run;
+
+        if create_issue {
+            let mut sentry_event = convert_tracing_event(event, &integration.options);
+            if let Some(timestamp) = timestamp {
+                sentry_event.timestamp = timestamp.into();
+            }
+            enrich_event(&mut sentry_event, integration);
+            integration.export.write(&sentry_event);
+
+            if dry_run.print() {
+                print_dry_run("event", &sentry_event);
+            }
+            if dry_run.send() {
+                // Include the breadcrumbs preceding the event.
+                flush_breadcrumbs();
+                mark_own_event(&mut sentry_event);
+
+                let attachment = if integration.options.attach_files {
+                    attachment_from_event(event, integration.options.max_attachment_bytes)
+                } else {
+                    None
+                };
+
+                match attachment {
+                    Some(attachment) => {
+                        capture_event_with_attachment(hub, sentry_event, attachment)
+                    }
+                    None => {
+                        hub.capture_event(sentry_event);
+                    }
+                }
+            }
+        }
+
+        if emit_breadcrumb {
+            let mut breadcrumb = breadcrumb_from_event(event, &integration.options);
+            if let Some(timestamp) = timestamp {
+                breadcrumb.timestamp = timestamp.into();
+            }
+            add_breadcrumb(integration, bound_hub, hub, breadcrumb);
+        }
+
+        true
+    })
+}
+
+/// Reports that an event was not recorded because the
+/// [TracingIntegration] is not (yet) enabled, once.
+fn warn_not_recorded() {
+    if !WARNED_NOT_RECORDED.swap(true, Ordering::Relaxed) {
+        eprintln!("Tracing event was not recorded by sentry because it has no `TracingIntegration` applied. This is only reported once.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use sentry_core::protocol::{SessionStatus, SessionUpdate};
+    use sentry_core::{Breadcrumb, ClientOptions, Hub};
+    use tracing::{span, Event, Metadata, Subscriber};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{SentryLayer, SentrySpanLayer};
+    use crate::test::{
+        with_captured, with_captured_client, with_captured_options, with_captured_subscriber,
+    };
+    use crate::{
+        BufferOverflow, DryRun, FieldDestination, FieldRoute, SpanCulprit, TracingIntegration,
+        TracingIntegrationOptions,
+    };
+
+    #[derive(Debug)]
+    struct ConfigError;
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("ConfigError: missing key")
+        }
+    }
+
+    impl std::error::Error for ConfigError {}
+
+    #[tracing::instrument(err)]
+    fn load_config() -> Result<(), ConfigError> {
+        Err(ConfigError)
+    }
+
+    #[tracing::instrument(err(Debug))]
+    fn parse_port(port: &str) -> Result<u16, std::num::ParseIntError> {
+        port.parse()
+    }
+
+    /// A Display formatted error whose message is not led by a type.
+    #[derive(Debug)]
+    struct OddError;
+
+    impl fmt::Display for OddError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("\tééé")
+        }
+    }
+
+    #[tracing::instrument(err)]
+    fn odd() -> Result<(), OddError> {
+        Err(OddError)
+    }
+
+    fn instrument_error_options() -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            instrument_errors: true,
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    #[test]
+    fn instrument_errors_are_only_attributed_when_enabled() {
+        let captured = with_captured(|_| {
+            let _ = load_config();
+        });
+
+        let event = &captured.events[0];
+        assert_ne!(event.exception.values[0].ty, "ConfigError");
+        assert_eq!(event.culprit, None);
+    }
+
+    #[tracing::instrument]
+    fn connect() {
+        other_module::report(ConfigError);
+    }
+
+    mod other_module {
+        pub fn report(error: super::ConfigError) {
+            tracing::error!(target: "other", error = %error);
+        }
+    }
+
+    #[test]
+    fn error_events_of_other_callsites_are_not_attributed() {
+        let captured = with_captured_options(instrument_error_options(), |_| connect());
+
+        let event = &captured.events[0];
+        assert_ne!(event.exception.values[0].ty, "ConfigError");
+        assert_eq!(event.culprit, None);
+    }
+
+    #[test]
+    fn display_error_is_split_into_type_and_message() {
+        let captured = with_captured_options(instrument_error_options(), |_| {
+            let _ = load_config();
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        let event = &captured.events[0];
+        let exception = &event.exception.values[0];
+        assert_eq!(exception.ty, "ConfigError");
+        assert_eq!(exception.value.as_deref(), Some("missing key"));
+        assert_eq!(
+            exception.module.as_deref(),
+            Some("sentry_tracing::layer::tests")
+        );
+        assert_eq!(
+            event.culprit.as_deref(),
+            Some("sentry_tracing::layer::tests::load_config")
+        );
+    }
+
+    #[test]
+    fn debug_error_keeps_its_formatting() {
+        let captured = with_captured_options(instrument_error_options(), |_| {
+            let _ = parse_port("http");
+        });
+
+        let exception = &captured.events[0].exception.values[0];
+        assert_eq!(exception.ty, "ParseIntError");
+        assert_eq!(
+            exception.value.as_deref(),
+            Some("ParseIntError { kind: InvalidDigit }")
+        );
+    }
+
+    #[test]
+    fn error_without_type_is_named_after_the_function() {
+        let captured = with_captured_options(instrument_error_options(), |_| {
+            let _ = odd();
+        });
+
+        let exception = &captured.events[0].exception.values[0];
+        assert_eq!(exception.ty, "[sentry_tracing::layer::tests] odd error");
+        assert_eq!(exception.value.as_deref(), Some("\tééé"));
+    }
+
+    /// A subscriber which does not implement `LookupSpan`.
+    struct NoLookup;
+
+    impl Subscriber for NoLookup {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn sentry_layer_works_without_lookup_span() {
+        let subscriber = NoLookup.with(SentryLayer);
+        let options = TracingIntegrationOptions {
+            span_contexts: true,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_subscriber(options, subscriber, |_| {
+            let span = tracing::info_span!("request", id = 1);
+            let _enter = span.enter();
+            tracing::info!("Generates a breadcrumb");
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.breadcrumbs.len(), 2);
+        // Events are not correlated with spans.
+        assert!(!captured.events[0].contexts.contains_key("span.request"));
+    }
+
+    #[test]
+    fn span_layer_correlates_events_with_spans() {
+        let options = TracingIntegrationOptions {
+            span_contexts: true,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let span = tracing::info_span!("request", id = 1);
+            let _enter = span.enter();
+            tracing::error!("Generates an event");
+        });
+
+        assert!(captured.events[0].contexts.contains_key("span.request"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "opentelemetry"))]
+    fn nearest_traced_transaction_requires_opentelemetry() {
+        let options = TracingIntegrationOptions {
+            transaction_name: crate::TransactionName::NearestTraced,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let span = tracing::info_span!("request");
+            let _enter = span.enter();
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events[0].transaction, None);
+    }
+
+    #[test]
+    fn callsites_are_reported_for_spans_without_fields() {
+        let options = TracingIntegrationOptions {
+            span_contexts: true,
+            span_callsites: true,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let span = tracing::info_span!("request");
+            let _enter = span.enter();
+            tracing::error!("Generates an event");
+        });
+
+        let context = serde_json::to_value(&captured.events[0].contexts["span.request"]).unwrap();
+        assert_eq!(context["target"], "sentry_tracing::layer::tests");
+        assert!(context["code.lineno"].is_number());
+    }
+
+    fn http_breadcrumb_options() -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            emit_http_breadcrumbs: true,
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    fn http_breadcrumbs(captured: &crate::test::CapturedData) -> usize {
+        let is_http = |breadcrumb: &&Breadcrumb| breadcrumb.ty == "http";
+        captured.breadcrumbs.iter().filter(is_http).count()
+    }
+
+    #[test]
+    fn http_breadcrumbs_are_emitted_for_client_spans() {
+        let captured = with_captured_options(http_breadcrumb_options(), |_| {
+            let _ = tracing::info_span!(
+                "request",
+                otel.kind = "client",
+                http.method = "GET",
+                http.url = "https://example.com"
+            );
+            let _ = tracing::info_span!("request", http.method = "GET", http.url = "/");
+            let _ = tracing::info_span!(
+                "request",
+                otel.kind = "server",
+                http.method = "GET",
+                http.url = "/"
+            );
+        });
+
+        assert_eq!(http_breadcrumbs(&captured), 1);
+        assert_eq!(captured.breadcrumbs[0].data["url"], "https://example.com");
+    }
+
+    #[test]
+    fn http_breadcrumbs_respect_the_breadcrumb_options() {
+        let span = || {
+            let _ = tracing::debug_span!(
+                "request",
+                otel.kind = "client",
+                http.method = "GET",
+                http.url = "https://example.com"
+            );
+        };
+        let options = TracingIntegrationOptions {
+            emit_breadcrumbs: false,
+            ..http_breadcrumb_options()
+        };
+        let captured = with_captured_options(options, |_| span());
+        assert_eq!(http_breadcrumbs(&captured), 0);
+
+        // The default filter leaves out debug spans.
+        let captured = with_captured_options(http_breadcrumb_options(), |_| span());
+        assert_eq!(http_breadcrumbs(&captured), 0);
+    }
+
+    #[test]
+    fn sentry_layer_works_as_a_unit_struct() {
+        let subscriber = tracing_subscriber::registry().with(SentryLayer);
+        let options = TracingIntegrationOptions::default();
+        let captured = with_captured_subscriber(options, subscriber, |_| {
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+    }
+
+    #[test]
+    fn span_layer_captures_into_its_hub() {
+        let captured = with_captured(|sink| {
+            let subscriber =
+                tracing_subscriber::registry().with(SentrySpanLayer::with_hub(sink.hub().clone()));
+            let other = Arc::new(Hub::new(None, Arc::default()));
+            tracing::subscriber::with_default(subscriber, || {
+                Hub::run(other, || tracing::error!("Generates an event"))
+            });
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.breadcrumbs.len(), 1);
+    }
+
+    #[test]
+    fn span_environment_ignores_renames_routes_and_private_fields() {
+        let environment = |options| {
+            let captured = with_captured_options(options, |_| {
+                let _span =
+                    tracing::info_span!("request", sentry.environment = "staging").entered();
+                tracing::error!("Generates an event");
+            });
+            captured.events[0].environment.clone()
+        };
+
+        let options = TracingIntegrationOptions {
+            field_renames: [("sentry.environment".to_owned(), "env".to_owned())].into(),
+            ..TracingIntegrationOptions::default()
+        };
+        assert_eq!(environment(options).as_deref(), Some("staging"));
+
+        let options = TracingIntegrationOptions {
+            field_routes: vec![FieldRoute::new("sentry.", FieldDestination::Tag)],
+            ..TracingIntegrationOptions::default()
+        };
+        assert_eq!(environment(options).as_deref(), Some("staging"));
+
+        let options = TracingIntegrationOptions {
+            private_field_prefix: Some("sentry.".to_owned()),
+            ..TracingIntegrationOptions::default()
+        };
+        assert_eq!(environment(options).as_deref(), Some("staging"));
+    }
+
+    #[test]
+    fn span_environment_ignores_filtered_spans() {
+        let captured = with_captured(|_| {
+            let _outer = tracing::info_span!("outer", sentry.environment = "production").entered();
+            let _inner =
+                tracing::info_span!("inner", sentry.environment = "staging", sentry.skip = true)
+                    .entered();
+            tracing::error!("Generates an event");
+        });
+        assert_eq!(
+            captured.events[0].environment.as_deref(),
+            Some("production")
+        );
+
+        let options = TracingIntegrationOptions {
+            span_filter: Some(Box::new(|metadata, _| metadata.name() != "request")),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let _span = tracing::info_span!("request", sentry.environment = "staging").entered();
+            tracing::error!("Generates an event");
+        });
+        assert_ne!(captured.events[0].environment.as_deref(), Some("staging"));
+    }
+
+    /// Runs `f` with root span sessions tracked, returning the session
+    /// updates which were sent.
+    fn root_span_sessions<F: FnOnce()>(f: F) -> Vec<SessionUpdate<'static>> {
+        let options = TracingIntegrationOptions {
+            track_root_span_sessions: true,
+            ..TracingIntegrationOptions::default()
+        };
+        let client_options = ClientOptions {
+            release: Some("1.0.0".into()),
+            ..ClientOptions::default()
+        };
+        let subscriber = tracing_subscriber::registry().with(SentrySpanLayer::default());
+        let integration = TracingIntegration::new(options);
+        let captured = with_captured_client(client_options, integration, subscriber, |sink| {
+            f();
+            // Session updates are only sent once the client is dropped.
+            sink.hub().bind_client(None);
+        });
+        captured.sessions
+    }
+
+    #[test]
+    fn root_spans_end_their_session() {
+        let sessions = root_span_sessions(|| {
+            let _span = tracing::info_span!("request").entered();
+        });
+
+        let session = sessions.last().expect("the session is sent");
+        assert_eq!(session.status, SessionStatus::Exited);
+        assert_eq!(session.errors, 0);
+    }
+
+    #[test]
+    fn root_span_sessions_count_errors() {
+        let sessions = root_span_sessions(|| {
+            let _span = tracing::info_span!("request").entered();
+            tracing::error!("Fails the request");
+        });
+
+        let session = sessions.last().expect("the session is sent");
+        assert_eq!(session.status, SessionStatus::Exited);
+        assert_eq!(session.errors, 1);
+    }
+
+    #[test]
+    fn root_span_sessions_end_while_the_layer_is_disabled() {
+        let sessions = root_span_sessions(|| {
+            let span = tracing::info_span!("request");
+            SentrySpanLayer::with_current(|layer| layer.set_enabled(false))
+                .expect("the layer is installed");
+            drop(span);
+        });
+
+        let session = sessions.last().expect("the session is sent");
+        assert_eq!(session.status, SessionStatus::Exited);
+    }
+
+    #[test]
+    fn buffered_events_are_replayed_when_closing() {
+        let captured = with_captured(|_| {
+            let subscriber = tracing_subscriber::registry()
+                .with(SentrySpanLayer::new().pre_init_buffer(10, BufferOverflow::DropOldest));
+            tracing::subscriber::with_default(subscriber, || {
+                let unbound = Arc::new(Hub::new(None, Arc::default()));
+                Hub::run(unbound, || {
+                    let _span = tracing::info_span!("request").entered();
+                    tracing::debug!("Filtered out");
+                    tracing::error!("Buffered");
+                });
+                assert!(crate::close(Duration::from_secs(1)));
+            });
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("message=Buffered")
+        );
+        assert_eq!(captured.breadcrumbs.len(), 1);
+    }
+
+    #[test]
+    fn span_layer_can_be_disabled_at_runtime() {
+        let captured = with_captured(|sink| {
+            SentrySpanLayer::with_current(|layer| layer.set_enabled(false))
+                .expect("the layer is installed");
+            tracing::error!("Dropped");
+            assert!(sink.take().events.is_empty());
+
+            SentrySpanLayer::with_current(|layer| layer.set_enabled(true));
+            tracing::error!("Captured");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("message=Captured")
+        );
+        assert!(captured
+            .breadcrumbs
+            .iter()
+            .all(|breadcrumb| breadcrumb.message.as_deref() != Some("message=Dropped")));
+    }
+
+    #[test]
+    fn with_current_requires_a_span_layer() {
+        let subscriber = tracing_subscriber::registry().with(SentryLayer);
+        let found =
+            tracing::subscriber::with_default(subscriber, || SentrySpanLayer::with_current(|_| ()));
+        assert_eq!(found, None);
+    }
+
+    /// Records a value for a span which does not exist.
+    fn record_missing_span() {
+        let span = tracing::info_span!("request", user = tracing::field::Empty);
+        let metadata = span.metadata().expect("the span is enabled");
+        let field = metadata.fields().field("user").expect("the field exists");
+        let values = [(&field, Some(&"alice" as &dyn tracing::Value))];
+        let values = metadata.fields().value_set(&values);
+        tracing::dispatcher::get_default(|dispatch| {
+            dispatch.record(&span::Id::from_u64(u64::MAX), &span::Record::new(&values))
+        });
+    }
+
+    #[test]
+    fn missing_spans_are_skipped_when_resilient() {
+        let captured = with_captured(|_| {
+            record_missing_span();
+            tracing::error!("Still captured");
+        });
+
+        assert!(super::WARNED_SPAN_NOT_FOUND.load(Ordering::Relaxed));
+        assert_eq!(captured.events.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Span not found")]
+    fn missing_spans_panic_unless_resilient() {
+        let options = TracingIntegrationOptions {
+            resilient: false,
+            ..TracingIntegrationOptions::default()
+        };
+        with_captured_options(options, |_| record_missing_span());
+    }
+
+    #[test]
+    fn span_culprit_is_derived_from_the_span() {
+        let options = TracingIntegrationOptions {
+            span_culprit: SpanCulprit::Span,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let query = tracing::info_span!("db.query", description = "SELECT * FROM users");
+            query.in_scope(|| tracing::error!("Failed"));
+            tracing::info_span!("request").in_scope(|| tracing::error!("Failed"));
+            tracing::error!("Failed");
+        });
+
+        let culprits: Vec<_> = captured
+            .events
+            .iter()
+            .map(|e| e.culprit.as_deref())
+            .collect();
+        assert_eq!(
+            culprits,
+            [Some("db.query: SELECT * FROM users"), Some("request"), None]
+        );
+    }
+
+    #[test]
+    fn span_culprit_callback_is_given_the_span_fields() {
+        let options = TracingIntegrationOptions {
+            span_culprit: SpanCulprit::Callback(Box::new(|metadata, fields| {
+                let route = fields.get("route")?.as_str()?;
+                Some(format!("{} {}", metadata.name(), route))
+            })),
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            let request = tracing::info_span!("request", route = "/users");
+            request.in_scope(|| tracing::error!("Failed"));
+            tracing::info_span!("request").in_scope(|| tracing::error!("Failed"));
+        });
+
+        assert_eq!(
+            captured.events[0].culprit.as_deref(),
+            Some("request /users")
+        );
+        assert_eq!(captured.events[1].culprit, None);
+    }
+
+    fn dry_run_options(dry_run: DryRun) -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            dry_run,
+            ..TracingIntegrationOptions::default()
         }
     }
+
+    #[test]
+    fn dry_runs_only_send_when_not_print_only() {
+        let generate = |_: &_| {
+            tracing::info!("Generates a breadcrumb");
+            tracing::error!("Generates an event");
+        };
+
+        let captured = with_captured_options(dry_run_options(DryRun::Print), generate);
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.breadcrumbs.len(), 2);
+
+        let captured = with_captured_options(dry_run_options(DryRun::PrintOnly), generate);
+        assert!(captured.events.is_empty());
+        assert!(captured.breadcrumbs.is_empty());
+    }
+
+    #[test]
+    fn dry_run_modes_print_and_send() {
+        assert!(!DryRun::Disabled.print() && DryRun::Disabled.send());
+        assert!(DryRun::Print.print() && DryRun::Print.send());
+        assert!(DryRun::PrintOnly.print() && !DryRun::PrintOnly.send());
+    }
 }
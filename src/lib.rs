@@ -19,10 +19,45 @@
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 
+mod attachments;
+mod breadcrumb_batch;
+mod budget;
+mod clock;
 mod converters;
+mod export;
+mod guard;
 mod integration;
 mod layer;
+#[cfg(feature = "log")]
+mod log_bridge;
+#[cfg(feature = "opentelemetry")]
+mod opentelemetry;
+mod panic;
+mod pre_init;
+mod sampling;
+mod span_data;
+#[cfg(any(test, feature = "test"))]
+pub mod test;
+mod watchdog;
 
-pub use converters::{breadcrumb_from_event, convert_tracing_event};
-pub use integration::{TracingIntegration, TracingIntegrationOptions};
-pub use layer::SentryLayer;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use converters::{
+    breadcrumb_from_event, convert_tracing_event, FieldVisitor, FieldVisitorConfig,
+    FieldVisitorResult,
+};
+#[cfg(feature = "ctrlc")]
+pub use guard::close_on_ctrlc;
+pub use guard::{close, close_guard, CloseGuard};
+pub use integration::{
+    reload_filter, DryRun, FieldDestination, FieldRoute, JsonFieldParsing, SpanCulprit,
+    TracingIntegration, TracingIntegrationOptions, TransactionName,
+};
+pub use layer::{SentryLayer, SentrySpanLayer};
+#[cfg(feature = "log")]
+pub use log_bridge::{init_log_bridge, init_log_bridge_with_level};
+#[cfg(feature = "opentelemetry")]
+pub use opentelemetry::is_current_trace_sampled;
+pub use panic::panic_handler;
+pub use pre_init::BufferOverflow;
+pub use sampling::{ParseSampleRatesError, SampleRates};
+pub use span_data::{span_data, SpanData};
@@ -0,0 +1,49 @@
+use tracing_log::log::{LevelFilter, SetLoggerError};
+use tracing_log::LogTracer;
+
+/// Installs a [LogTracer] as the global `log` logger, forwarding
+/// records at `Info` level and above to `tracing`, where they are
+/// captured by the [SentryLayer](crate::SentryLayer) like any other
+/// tracing event.
+///
+/// **Feature:** `log` (*disabled by default*)
+///
+/// This replaces the need for the separate `sentry-log` integration
+/// in applications which use both `log` and `tracing`. Returns an
+/// error if a global logger has already been set.
+pub fn init_log_bridge() -> Result<(), SetLoggerError> {
+    init_log_bridge_with_level(LevelFilter::Info)
+}
+
+/// Installs a [LogTracer] as the global `log` logger, forwarding
+/// records at the specified `level` and above to `tracing`.
+///
+/// **Feature:** `log` (*disabled by default*)
+///
+/// See [init_log_bridge()].
+pub fn init_log_bridge_with_level(level: LevelFilter) -> Result<(), SetLoggerError> {
+    LogTracer::builder().with_max_level(level).init()
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_log::log::{self, LevelFilter};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::test::with_captured_integration;
+    use crate::{SentrySpanLayer, TracingIntegration};
+
+    #[test]
+    fn log_records_are_forwarded_from_the_level() {
+        let integration = TracingIntegration::default().log_bridge(LevelFilter::Warn);
+        let subscriber = tracing_subscriber::registry().with(SentrySpanLayer::default());
+
+        let captured = with_captured_integration(integration, subscriber, |_| {
+            log::info!("Not forwarded");
+            log::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.breadcrumbs.len(), 1);
+    }
+}
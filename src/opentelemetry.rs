@@ -0,0 +1,184 @@
+use opentelemetry::sdk::trace::SamplingDecision;
+use opentelemetry::trace::{SpanBuilder, SpanId, TraceContextExt};
+use sentry_core::protocol::TraceContext;
+use sentry_core::types::Uuid;
+use tracing_subscriber::registry::{LookupSpan, Registry, SpanRef};
+
+/// Reads the trace and span ids assigned by `tracing-opentelemetry` to
+/// the specified `span`, so that events captured within it can be
+/// correlated with the OpenTelemetry trace.
+///
+/// Returns `None` if the span has no OpenTelemetry data, or if the
+/// ids are invalid (e.g. when a no-op tracer is installed).
+pub(crate) fn trace_context<'a, S>(span: &SpanRef<'a, S>) -> Option<TraceContext>
+where
+    S: for<'b> LookupSpan<'b>,
+{
+    let extensions = span.extensions();
+    let builder = extensions.get::<SpanBuilder>()?;
+
+    let parent_span_context = builder.parent_context.span().span_context().clone();
+
+    // Only root spans have their trace id assigned in the builder,
+    // the rest inherit it from their parent context.
+    let trace_id = builder.trace_id.or_else(|| {
+        if parent_span_context.is_valid() {
+            Some(parent_span_context.trace_id())
+        } else {
+            None
+        }
+    })?;
+    let span_id = builder.span_id?;
+
+    if trace_id.to_u128() == 0 || span_id.to_u64() == 0 {
+        return None;
+    }
+
+    Some(TraceContext {
+        trace_id: Uuid::from_u128(trace_id.to_u128()),
+        span_id: span_id_to_uuid(span_id),
+        parent_span_id: if parent_span_context.is_valid() {
+            Some(
+                span_id_to_uuid(parent_span_context.span_id())
+                    .to_simple()
+                    .to_string(),
+            )
+        } else {
+            None
+        },
+        ..TraceContext::default()
+    })
+}
+
+/// Converts an OpenTelemetry span id into the uuid representation of
+/// span ids in Sentry data, so that the ids of spans and their parents
+/// are represented the same way: the 16 hex digits of the span id,
+/// zero-padded to 32.
+fn span_id_to_uuid(span_id: SpanId) -> Uuid {
+    Uuid::from_u128(u128::from(span_id.to_u64()))
+}
+
+/// Whether the OpenTelemetry trace of the `span` is sampled.
+///
+/// Returns `None` if the span has no OpenTelemetry data, or if the
+/// sampling decision has not been made yet (which
+/// `tracing-opentelemetry` defers for root spans until they close, or
+/// their context is requested via `OpenTelemetrySpanExt::context()`).
+pub(crate) fn is_sampled<'a, S>(span: &SpanRef<'a, S>) -> Option<bool>
+where
+    S: for<'b> LookupSpan<'b>,
+{
+    let extensions = span.extensions();
+    let builder = extensions.get::<SpanBuilder>()?;
+
+    if let Some(sampling_result) = &builder.sampling_result {
+        return Some(sampling_result.decision == SamplingDecision::RecordAndSample);
+    }
+
+    let parent_span_context = builder.parent_context.span().span_context().clone();
+    if parent_span_context.is_valid() {
+        Some(parent_span_context.is_sampled())
+    } else {
+        None
+    }
+}
+
+/// Whether the OpenTelemetry trace of the current span is sampled, so
+/// that application code can skip collecting expensive diagnostics
+/// when the trace will not be exported anyway.
+///
+/// Returns `None` if there is no current span with OpenTelemetry
+/// data, or if the sampling decision has not been made yet (which
+/// `tracing-opentelemetry` defers for root spans until they close, or
+/// their context is requested via `OpenTelemetrySpanExt::context()`).
+pub fn is_current_trace_sampled() -> Option<bool> {
+    tracing::Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let span = dispatch.downcast_ref::<Registry>()?.span(id)?;
+            is_sampled(&span)
+        })
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::TracerProvider as _;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::test::{with_captured_integration, with_captured_subscriber};
+    use crate::{SentrySpanLayer, TracingIntegration, TracingIntegrationOptions, TransactionName};
+
+    #[test]
+    fn span_ids_are_represented_consistently() {
+        let provider = opentelemetry::sdk::trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test", None);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .with(SentrySpanLayer::default());
+
+        let captured =
+            with_captured_subscriber(TracingIntegrationOptions::default(), subscriber, |_| {
+                let parent = tracing::info_span!("parent");
+                let _parent = parent.enter();
+                tracing::error!("In the parent");
+                let child = tracing::info_span!("child");
+                let _child = child.enter();
+                tracing::error!("In the child");
+            });
+
+        let trace =
+            |index: usize| serde_json::to_value(&captured.events[index].contexts["trace"]).unwrap();
+        let (parent, child) = (trace(0), trace(1));
+        assert_eq!(parent["span_id"].as_str().unwrap().len(), 32);
+        assert_eq!(child["parent_span_id"], parent["span_id"]);
+        assert_eq!(child["trace_id"], parent["trace_id"]);
+    }
+
+    #[test]
+    fn events_of_unsampled_traces_are_only_breadcrumbs() {
+        let provider = opentelemetry::sdk::trace::TracerProvider::builder()
+            .with_config(
+                opentelemetry::sdk::trace::config()
+                    .with_sampler(opentelemetry::sdk::trace::Sampler::AlwaysOff),
+            )
+            .build();
+        let tracer = provider.tracer("test", None);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .with(SentrySpanLayer::default());
+        let integration = TracingIntegration::default().sampled_trace_events_only();
+
+        let captured = with_captured_integration(integration, subscriber, |_| {
+            let span = tracing::info_span!("request");
+            // Makes the sampling decision of the root span.
+            let _ = span.context();
+            let _enter = span.enter();
+            tracing::error!("Not sampled");
+        });
+
+        assert!(captured.events.is_empty());
+        assert_eq!(captured.breadcrumbs.len(), 1);
+    }
+
+    #[test]
+    fn transaction_is_named_after_the_nearest_traced_span() {
+        let provider = opentelemetry::sdk::trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test", None);
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .with(SentrySpanLayer::default());
+        let options = TracingIntegrationOptions {
+            transaction_name: TransactionName::NearestTraced,
+            ..TracingIntegrationOptions::default()
+        };
+
+        let captured = with_captured_subscriber(options, subscriber, |_| {
+            let span = tracing::info_span!("request");
+            let _enter = span.enter();
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events[0].transaction.as_deref(), Some("request"));
+    }
+}
@@ -0,0 +1,213 @@
+#[allow(deprecated)]
+use std::panic::{self, PanicInfo};
+use std::sync::Once;
+
+use sentry_backtrace::current_stacktrace;
+use sentry_core::protocol::{Context, Event, Exception, Level, Map, Mechanism};
+use tracing_subscriber::registry::{LookupSpan, Registry, SpanRef};
+
+use crate::breadcrumb_batch::flush_breadcrumbs;
+use crate::converters::{mark_own_event, SpanFields};
+use crate::layer::{print_dry_run, CAPTURE};
+use crate::TracingIntegration;
+
+static INIT: Once = Once::new();
+
+/// Installs [panic_handler()] as the panic hook, calling the previous
+/// hook afterwards. Only the first call has an effect.
+pub(crate) fn install_panic_hook() {
+    INIT.call_once(|| {
+        let next = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            panic_handler(info);
+            next(info);
+        }));
+    });
+}
+
+/// A panic handler which captures the panic as a Sentry event,
+/// enriched with the stack of tracing spans the panic occurred in.
+///
+/// This is installed automatically when
+/// [TracingIntegrationOptions::capture_panics](crate::TracingIntegrationOptions::capture_panics)
+/// is enabled, and replaces the `sentry-panic` integration.
+///
+/// The event is printed and sent according to the
+/// [dry_run](crate::TracingIntegrationOptions::dry_run) option.
+#[allow(deprecated)]
+pub fn panic_handler(info: &PanicInfo<'_>) {
+    if !CAPTURE {
+        return;
+    }
+    sentry_core::with_integration(|integration: &TracingIntegration, hub| {
+        // The hook is global, while the hub may be bound to a client
+        // which does not capture panics.
+        if !integration.options.capture_panics {
+            return;
+        }
+        flush_breadcrumbs();
+        let mut event = event_from_panic_info(integration, info);
+        integration.export.write(&event);
+        let dry_run = integration.options.dry_run;
+        if dry_run.print() {
+            print_dry_run("event", &event);
+        }
+        if dry_run.send() {
+            mark_own_event(&mut event);
+            hub.capture_event(event);
+        }
+    });
+}
+
+#[allow(deprecated)]
+fn message_from_panic_info<'a>(info: &'a PanicInfo<'_>) -> &'a str {
+    match info.payload().downcast_ref::<&'static str>() {
+        Some(s) => s,
+        None => match info.payload().downcast_ref::<String>() {
+            Some(s) => &s[..],
+            None => "Box<Any>",
+        },
+    }
+}
+
+/// Creates an event from the given panic info, with the current span
+/// stack in the `tracing` context, leaving out the spans which are not
+/// [enabled](TracingIntegration::span_enabled).
+///
+/// The stacktrace is calculated from the current frame.
+#[allow(deprecated)]
+fn event_from_panic_info(integration: &TracingIntegration, info: &PanicInfo<'_>) -> Event<'static> {
+    let mut event = Event {
+        exception: vec![Exception {
+            ty: "panic".into(),
+            mechanism: Some(Mechanism {
+                ty: "panic".into(),
+                handled: Some(false),
+                ..Default::default()
+            }),
+            value: Some(message_from_panic_info(info).to_owned()),
+            stacktrace: current_stacktrace(),
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Fatal,
+        ..Default::default()
+    };
+
+    tracing::Span::current().with_subscriber(|(id, dispatch)| {
+        let span = match dispatch
+            .downcast_ref::<Registry>()
+            .and_then(|registry| registry.span(id))
+        {
+            Some(span) => span,
+            None => return,
+        };
+
+        #[cfg(feature = "opentelemetry")]
+        if let Some(trace_context) = crate::opentelemetry::trace_context(&span) {
+            event
+                .contexts
+                .insert("trace".into(), Context::Trace(Box::new(trace_context)));
+        }
+
+        let spans = span
+            .scope()
+            .from_root()
+            .filter_map(|span| span_to_json(integration, &span))
+            .collect::<Vec<_>>();
+
+        let mut tracing_context = Map::new();
+        tracing_context.insert("spans".into(), spans.into());
+        event
+            .contexts
+            .insert("tracing".into(), Context::Other(tracing_context));
+    });
+
+    event
+}
+
+/// Converts a span into a json object with its name, target and any
+/// recorded fields, unless the span is not
+/// [enabled](TracingIntegration::span_enabled).
+fn span_to_json<'a, S>(
+    integration: &TracingIntegration,
+    span: &SpanRef<'a, S>,
+) -> Option<serde_json::Value>
+where
+    S: for<'b> LookupSpan<'b>,
+{
+    let extensions = span.extensions();
+    let no_fields = SpanFields::default();
+    let fields = extensions.get::<SpanFields>();
+    if !integration.span_enabled(span.metadata(), fields.unwrap_or(&no_fields)) {
+        return None;
+    }
+
+    let mut value = serde_json::json!({
+        "name": span.name(),
+        "target": span.metadata().target(),
+    });
+    if let Some(fields) = fields {
+        value["fields"] = serde_json::json!(fields.values);
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use crate::test::with_captured_options;
+    use crate::{DryRun, TracingIntegrationOptions};
+
+    fn panicking_options(dry_run: DryRun) -> TracingIntegrationOptions {
+        TracingIntegrationOptions {
+            capture_panics: true,
+            dry_run,
+            ..TracingIntegrationOptions::default()
+        }
+    }
+
+    #[tracing::instrument(fields(sentry.skip = true))]
+    fn skipped() {
+        panic!("Skipped span");
+    }
+
+    #[tracing::instrument]
+    fn job(id: u32) {
+        skipped();
+    }
+
+    #[test]
+    fn panics_are_captured_with_the_span_stack() {
+        let captured = with_captured_options(panicking_options(DryRun::Disabled), |_| {
+            assert!(panic::catch_unwind(|| job(7)).is_err());
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        let event = &captured.events[0];
+        assert_eq!(event.level, sentry_core::Level::Fatal);
+        assert_eq!(
+            event.exception.values[0].value.as_deref(),
+            Some("Skipped span")
+        );
+        match &event.contexts["tracing"] {
+            sentry_core::protocol::Context::Other(context) => {
+                let spans = context["spans"].as_array().unwrap();
+                assert_eq!(spans.len(), 1);
+                assert_eq!(spans[0]["name"], "job");
+                assert_eq!(spans[0]["fields"]["id"], 7);
+            }
+            context => panic!("unexpected tracing context: {:?}", context),
+        }
+    }
+
+    #[test]
+    fn panics_are_not_sent_in_print_only_dry_runs() {
+        let captured = with_captured_options(panicking_options(DryRun::PrintOnly), |_| {
+            assert!(panic::catch_unwind(|| job(7)).is_err());
+        });
+
+        assert!(captured.events.is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError, TryLockError};
+use std::time::SystemTime;
+
+use tracing::field::{debug, DebugValue, Field, Value, Visit};
+use tracing::{Event, Metadata};
+
+use crate::{Clock, SystemClock};
+
+/// Which events are dropped once the buffer of a
+/// [SentrySpanLayer](crate::SentrySpanLayer) for events emitted before
+/// Sentry is initialized is full, see
+/// [SentrySpanLayer::pre_init_buffer()](crate::SentrySpanLayer::pre_init_buffer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferOverflow {
+    /// The oldest buffered event is dropped to make room for the new
+    /// one.
+    DropOldest,
+    /// The new event is dropped.
+    DropNewest,
+}
+
+/// A string which is formatted as is by `Debug`, to replay values which
+/// were recorded with their `Debug` representation.
+struct RawDebug(String);
+
+impl fmt::Debug for RawDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Display for RawDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for RawDebug {}
+
+/// An owned copy of the value of a field.
+enum BufferedValue {
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F64(f64),
+    Bool(bool),
+    Str(String),
+    Bytes(Box<[u8]>),
+    Error(Box<dyn Error + Send + Sync>),
+    Debug(DebugValue<RawDebug>),
+}
+
+impl BufferedValue {
+    fn as_value(&self) -> &dyn Value {
+        match self {
+            BufferedValue::I64(value) => value,
+            BufferedValue::U64(value) => value,
+            BufferedValue::I128(value) => value,
+            BufferedValue::U128(value) => value,
+            BufferedValue::F64(value) => value,
+            BufferedValue::Bool(value) => value,
+            BufferedValue::Str(value) => value,
+            BufferedValue::Bytes(value) => value,
+            BufferedValue::Error(value) => value,
+            BufferedValue::Debug(value) => value,
+        }
+    }
+}
+
+/// An event emitted while no client with the integration was bound.
+struct BufferedEvent {
+    metadata: &'static Metadata<'static>,
+    /// The values of the fields of the event, by field index.
+    values: Vec<Option<BufferedValue>>,
+    timestamp: SystemTime,
+}
+
+impl BufferedEvent {
+    fn new(event: &Event<'_>) -> Self {
+        let metadata = event.metadata();
+        let mut buffered = Self {
+            metadata,
+            values: metadata.fields().iter().map(|_| None).collect(),
+            timestamp: SystemClock.now(),
+        };
+        event.record(&mut buffered);
+        buffered
+    }
+
+    fn set(&mut self, field: &Field, value: BufferedValue) {
+        if let Some(slot) = self.values.get_mut(field.index()) {
+            *slot = Some(value);
+        }
+    }
+
+    /// Calls `f` with a copy of the original event (as a root event,
+    /// as its spans are not buffered).
+    fn replay<R>(&self, f: impl FnOnce(&Event<'_>) -> R) -> R {
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.as_ref().map(BufferedValue::as_value))
+            .collect::<Vec<_>>();
+        let value_set = self.metadata.fields().value_set_all(&values);
+        f(&Event::new_child_of(None, self.metadata, &value_set))
+    }
+}
+
+impl Visit for BufferedEvent {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, BufferedValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, BufferedValue::U64(value));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.set(field, BufferedValue::I128(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.set(field, BufferedValue::U128(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, BufferedValue::F64(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, BufferedValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, BufferedValue::Str(value.to_owned()));
+    }
+
+    fn record_bytes(&mut self, field: &Field, value: &[u8]) {
+        self.set(field, BufferedValue::Bytes(value.into()));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn Error + 'static)) {
+        let value = RawDebug(format!("{:?}", value));
+        self.set(field, BufferedValue::Error(Box::new(value)));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = RawDebug(format!("{:?}", value));
+        self.set(field, BufferedValue::Debug(debug(value)));
+    }
+}
+
+/// Events emitted while no client with the integration is bound (e.g.
+/// before `sentry::init` is called), which are replayed once one is.
+pub(crate) struct PreInitBuffer {
+    capacity: usize,
+    overflow: BufferOverflow,
+    events: Mutex<VecDeque<BufferedEvent>>,
+    /// Whether there are any buffered events, so that the lock is only
+    /// taken when they need to be replayed.
+    pending: AtomicBool,
+}
+
+impl PreInitBuffer {
+    pub fn new(capacity: usize, overflow: BufferOverflow) -> Self {
+        Self {
+            capacity,
+            overflow,
+            events: Mutex::new(VecDeque::new()),
+            pending: AtomicBool::new(false),
+        }
+    }
+
+    /// Buffers a copy of the `event`, according to the overflow policy
+    /// once the buffer is full.
+    pub fn push(&self, event: &Event<'_>) {
+        let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        if events.len() >= self.capacity {
+            match self.overflow {
+                BufferOverflow::DropOldest if self.capacity > 0 => {
+                    events.pop_front();
+                }
+                _ => return,
+            }
+        }
+        events.push_back(BufferedEvent::new(event));
+        self.pending.store(true, Ordering::Relaxed);
+    }
+
+    /// Calls `record` with each buffered event in order, along with the
+    /// time it was emitted, until it returns `false` because the event
+    /// could not be recorded yet, in which case it stays buffered.
+    pub fn replay(&self, mut record: impl FnMut(&Event<'_>, SystemTime) -> bool) {
+        if !self.pending.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // Events emitted while replaying (e.g. by the client) would try
+        // to replay the buffer again, while it is locked.
+        let mut events = match self.events.try_lock() {
+            Ok(events) => events,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        while let Some(event) = events.front() {
+            if !event.replay(|replayed| record(replayed, event.timestamp)) {
+                return;
+            }
+            events.pop_front();
+        }
+        self.pending.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    use super::{BufferOverflow, PreInitBuffer};
+
+    /// A layer which buffers every event.
+    struct Buffering(Arc<PreInitBuffer>);
+
+    impl<S: Subscriber> Layer<S> for Buffering {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            self.0.push(event);
+        }
+    }
+
+    #[derive(Default)]
+    struct Message(String);
+
+    impl Visit for Message {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    /// Buffers an event for each of the `messages`.
+    fn buffer(capacity: usize, overflow: BufferOverflow, messages: &[&str]) -> PreInitBuffer {
+        let buffer = Arc::new(PreInitBuffer::new(capacity, overflow));
+        let subscriber = tracing_subscriber::registry().with(Buffering(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            for message in messages {
+                tracing::info!("{}", message);
+            }
+        });
+        Arc::try_unwrap(buffer).unwrap_or_else(|_| panic!("the subscriber is dropped"))
+    }
+
+    /// Replays the buffered events, returning their messages and the
+    /// time they were emitted.
+    fn replay(buffer: &PreInitBuffer) -> Vec<(String, SystemTime)> {
+        let mut replayed = Vec::new();
+        buffer.replay(|event, timestamp| {
+            let mut message = Message::default();
+            event.record(&mut message);
+            replayed.push((message.0, timestamp));
+            true
+        });
+        replayed
+    }
+
+    fn messages(replayed: &[(String, SystemTime)]) -> Vec<&str> {
+        replayed
+            .iter()
+            .map(|(message, _)| message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn events_are_replayed_in_order_with_their_timestamps() {
+        let before = SystemTime::now();
+        let buffer = buffer(10, BufferOverflow::DropOldest, &["first", "second"]);
+        let after = SystemTime::now();
+
+        let replayed = replay(&buffer);
+        assert_eq!(messages(&replayed), ["first", "second"]);
+        assert!(replayed[0].1 >= before && replayed[0].1 <= replayed[1].1);
+        assert!(replayed[1].1 <= after);
+
+        // The buffer is emptied by replaying it.
+        assert!(replay(&buffer).is_empty());
+    }
+
+    #[test]
+    fn the_oldest_events_are_dropped_once_full() {
+        let buffer = buffer(2, BufferOverflow::DropOldest, &["first", "second", "third"]);
+        assert_eq!(messages(&replay(&buffer)), ["second", "third"]);
+    }
+
+    #[test]
+    fn the_newest_events_are_dropped_once_full() {
+        let buffer = buffer(2, BufferOverflow::DropNewest, &["first", "second", "third"]);
+        assert_eq!(messages(&replay(&buffer)), ["first", "second"]);
+    }
+
+    #[test]
+    fn nothing_is_buffered_without_capacity() {
+        let buffer = buffer(0, BufferOverflow::DropOldest, &["first"]);
+        assert!(replay(&buffer).is_empty());
+    }
+
+    #[test]
+    fn events_which_are_not_recorded_stay_buffered() {
+        let buffer = buffer(10, BufferOverflow::DropOldest, &["first", "second"]);
+
+        let mut attempts = 0;
+        buffer.replay(|_, _| {
+            attempts += 1;
+            false
+        });
+        assert_eq!(attempts, 1);
+
+        assert_eq!(messages(&replay(&buffer)), ["first", "second"]);
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Sample rates for events, which can be overridden for specific
+/// targets.
+///
+/// Can be parsed from a comma separated list of rates in a syntax
+/// similar to `EnvFilter` directives, where a bare rate sets the
+/// default, and `target=rate` sets the rate for events with that
+/// target (or a target nested within it, such as its submodules):
+///
+/// ```
+/// use sentry_tracing::SampleRates;
+///
+/// let rates: SampleRates = "1.0,chatty_crate=0.1,chatty_crate::quiet=0.5"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(rates.rate("my_app"), 1.0);
+/// assert_eq!(rates.rate("chatty_crate::db"), 0.1);
+/// assert_eq!(rates.rate("chatty_crate::quiet"), 0.5);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRates {
+    default: f32,
+    targets: Vec<(String, f32)>,
+}
+
+impl SampleRates {
+    /// Create new [SampleRates] with a `default` rate between `0.0`
+    /// and `1.0`, and no target specific rates.
+    pub fn new(default: f32) -> Self {
+        Self {
+            default,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Sets the `rate` for events with the specified `target`, or a
+    /// target nested within it.
+    pub fn with_target(mut self, target: impl Into<String>, rate: f32) -> Self {
+        self.targets.push((target.into(), rate));
+        self
+    }
+
+    /// The sample rate for events with the specified `target`, using
+    /// the most specific matching target rate.
+    pub fn rate(&self, target: &str) -> f32 {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, rate)| *rate)
+    }
+
+    /// Randomly decides whether an event with the specified `target`
+    /// is sampled.
+    pub(crate) fn sample(&self, target: &str) -> bool {
+        let rate = self.rate(target);
+        rate >= 1.0 || rand::random::<f32>() < rate
+    }
+}
+
+impl Default for SampleRates {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl From<f32> for SampleRates {
+    fn from(default: f32) -> Self {
+        Self::new(default)
+    }
+}
+
+/// An error parsing [SampleRates].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSampleRatesError {
+    directive: String,
+}
+
+impl fmt::Display for ParseSampleRatesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid sample rate directive \"{}\"", self.directive)
+    }
+}
+
+impl std::error::Error for ParseSampleRatesError {}
+
+impl FromStr for SampleRates {
+    type Err = ParseSampleRatesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rates = Self::default();
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let error = || ParseSampleRatesError {
+                directive: directive.to_owned(),
+            };
+            let parse_rate = |rate: &str| {
+                rate.trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .ok_or_else(error)
+            };
+
+            match directive.split_once('=') {
+                Some((target, rate)) => rates
+                    .targets
+                    .push((target.trim().to_owned(), parse_rate(rate)?)),
+                None => rates.default = parse_rate(directive)?,
+            }
+        }
+        Ok(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SampleRates;
+
+    #[test]
+    fn target_rates_match_nested_targets_only() {
+        let rates = SampleRates::new(0.5)
+            .with_target("app", 0.1)
+            .with_target("app::db", 0.2);
+
+        assert_eq!(rates.rate("app"), 0.1);
+        assert_eq!(rates.rate("app::http"), 0.1);
+        assert_eq!(rates.rate("app::db::pool"), 0.2);
+        // A prefix of the name of another target does not match.
+        assert_eq!(rates.rate("application"), 0.5);
+        assert_eq!(rates.rate("other"), 0.5);
+    }
+
+    #[test]
+    fn extreme_rates_are_deterministic() {
+        let rates = SampleRates::new(1.0).with_target("chatty", 0.0);
+        assert!((0..100).all(|_| rates.sample("app")));
+        assert!((0..100).all(|_| !rates.sample("chatty::db")));
+    }
+
+    #[test]
+    fn invalid_rates_are_rejected() {
+        assert!("1.5".parse::<SampleRates>().is_err());
+        assert!("app=-0.1".parse::<SampleRates>().is_err());
+        assert!("app=often".parse::<SampleRates>().is_err());
+    }
+}
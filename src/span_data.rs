@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+use crate::converters::SpanFields;
+
+/// The data recorded by the [SentrySpanLayer](crate::SentrySpanLayer)
+/// for a span, see [span_data()].
+#[derive(Debug, Clone, Default)]
+pub struct SpanData {
+    /// The name of the span.
+    pub name: &'static str,
+    /// The target of the span.
+    pub target: &'static str,
+    /// The recorded fields of the span, by name.
+    ///
+    /// Fields are only recorded when an option which uses them is
+    /// enabled, such as
+    /// [span_contexts](crate::TracingIntegrationOptions::span_contexts).
+    pub fields: BTreeMap<String, serde_json::Value>,
+    /// The values of the boolean `feature_flag.<flag name>` fields of
+    /// the span, by flag name.
+    pub feature_flags: BTreeMap<String, bool>,
+    /// The trace and span ids assigned to the span by
+    /// `tracing-opentelemetry`, with which events captured in it are
+    /// correlated.
+    ///
+    /// **Feature:** `opentelemetry` (*disabled by default*)
+    #[cfg(feature = "opentelemetry")]
+    pub trace_context: Option<sentry_core::protocol::TraceContext>,
+}
+
+/// Reads the data recorded by the
+/// [SentrySpanLayer](crate::SentrySpanLayer) for the `span`, so that
+/// other layers, exporters and middleware can correlate with or enrich
+/// what is captured for it.
+///
+/// Returns `None` if the span is disabled, or if the subscriber is not
+/// built on the `Registry`.
+///
+/// The data is limited to what the layer records for its own use:
+///
+/// * The [fields](SpanData::fields) are empty unless an option which
+///   uses them is enabled, such as
+///   [span_contexts](crate::TracingIntegrationOptions::span_contexts),
+///   or the span has `feature_flag.*` or `sentry.environment` fields.
+///   They are also empty for spans opened while the
+///   [SentrySpanLayer](crate::SentrySpanLayer) was not capturing, or
+///   with [record_spans](crate::TracingIntegrationOptions::record_spans)
+///   disabled.
+/// * The span has no trace or span id without the `opentelemetry`
+///   feature, as the layer does not create Sentry spans or
+///   transactions, and there is no operation (`op`) either.
+///
+/// # Examples
+///
+/// ```
+/// let span = tracing::info_span!("request", method = "GET");
+/// if let Some(data) = sentry_tracing::span_data(&span) {
+///     println!("{}: {:?}", data.name, data.fields.get("method"));
+/// }
+/// ```
+pub fn span_data(span: &tracing::Span) -> Option<SpanData> {
+    span.with_subscriber(|(id, dispatch)| {
+        let span = dispatch.downcast_ref::<Registry>()?.span(id)?;
+        let extensions = span.extensions();
+        let fields = extensions.get::<SpanFields>();
+        Some(SpanData {
+            name: span.name(),
+            target: span.metadata().target(),
+            fields: fields
+                .map(|fields| fields.values.clone())
+                .unwrap_or_default(),
+            feature_flags: fields
+                .map(|fields| fields.feature_flags.clone())
+                .unwrap_or_default(),
+            #[cfg(feature = "opentelemetry")]
+            trace_context: crate::opentelemetry::trace_context(&span),
+        })
+    })
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{with_captured, with_captured_options};
+    use crate::TracingIntegrationOptions;
+
+    #[test]
+    fn span_data_contains_the_recorded_fields() {
+        let options = TracingIntegrationOptions {
+            span_contexts: true,
+            ..TracingIntegrationOptions::default()
+        };
+        with_captured_options(options, |_| {
+            let span = tracing::info_span!("request", method = "GET", feature_flag.beta = true);
+            let data = super::span_data(&span).expect("the span is enabled");
+
+            assert_eq!(data.name, "request");
+            assert_eq!(data.target, module_path!());
+            assert_eq!(data.fields.get("method"), Some(&"GET".into()));
+            assert_eq!(data.feature_flags.get("beta"), Some(&true));
+        });
+    }
+
+    #[test]
+    fn span_data_fields_are_only_recorded_when_needed() {
+        with_captured(|_| {
+            let span = tracing::info_span!("request", method = "GET");
+            let data = super::span_data(&span).expect("the span is enabled");
+
+            assert_eq!(data.name, "request");
+            assert!(data.fields.is_empty());
+        });
+    }
+}
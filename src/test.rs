@@ -0,0 +1,328 @@
+//! Helpers for testing code instrumented with `tracing` against this
+//! integration.
+//!
+//! **Feature:** `test` (*disabled by default*)
+//!
+//! # Examples
+//!
+//! ```
+//! let captured = sentry_tracing::test::with_captured(|_sink| {
+//!     tracing::info!("Generates a breadcrumb");
+//!     tracing::error!("Generates an event");
+//! });
+//!
+//! assert_eq!(captured.events.len(), 1);
+//! // Both tracing events are recorded as breadcrumbs.
+//! assert_eq!(captured.breadcrumbs.len(), 2);
+//! ```
+//!
+//! Captured data can be compared against golden files, using
+//! [CapturedData::to_normalized_json()] to pin values which change
+//! from run to run:
+//!
+//! ```
+//! let captured = sentry_tracing::test::with_captured(|_sink| {
+//!     tracing::error!("Generates an event");
+//! });
+//!
+//! let json = captured.to_normalized_json();
+//! assert!(json.contains(r#""event_id": "[event_id]""#));
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use sentry_core::protocol::{Attachment, EnvelopeItem, Event, SessionUpdate, Transaction};
+use sentry_core::test::TestTransport;
+use sentry_core::{Breadcrumb, ClientOptions, Envelope, Hub};
+use serde_json::Value;
+use tracing::Subscriber;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::{SentrySpanLayer, TracingIntegration, TracingIntegrationOptions};
+
+const TEST_DSN: &str = "https://public@sentry.invalid/1";
+
+/// The Sentry data captured while running a closure with
+/// [with_captured()].
+#[derive(Debug, Default, Clone)]
+pub struct CapturedData {
+    /// Events that were sent to Sentry.
+    pub events: Vec<Event<'static>>,
+    /// Breadcrumbs that were added to the scope.
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// Transactions that were sent to Sentry.
+    pub transactions: Vec<Transaction<'static>>,
+    /// Attachments that were sent to Sentry along with events.
+    pub attachments: Vec<Attachment>,
+    /// Session updates that were sent to Sentry, which happens once
+    /// the client is dropped.
+    pub sessions: Vec<SessionUpdate<'static>>,
+}
+
+/// Keys of values which differ between otherwise identical runs, and
+/// are replaced with a placeholder by [CapturedData::normalized()].
+const VOLATILE_KEYS: &[&str] = &[
+    "event_id",
+    "timestamp",
+    "start_timestamp",
+    "trace_id",
+    "span_id",
+    "parent_span_id",
+    "duration",
+    "stacktrace",
+];
+
+impl CapturedData {
+    /// Serializes the captured data to a json [Value], replacing
+    /// volatile values (timestamps, ids, durations and stacktraces)
+    /// with a placeholder in the form `"[key]"`.
+    pub fn normalized(&self) -> Value {
+        let mut value = serde_json::json!({
+            "events": self.events,
+            "breadcrumbs": self.breadcrumbs,
+            "transactions": self.transactions,
+        });
+        normalize_value(&mut value);
+        value
+    }
+
+    /// Serializes the [normalized](CapturedData::normalized()) captured
+    /// data to a pretty printed json string, suitable for snapshot
+    /// testing.
+    pub fn to_normalized_json(&self) -> String {
+        serde_json::to_string_pretty(&self.normalized())
+            .expect("Captured data should always be serializable to json")
+    }
+
+    fn extend_from_envelopes(&mut self, envelopes: Vec<Envelope>) {
+        for envelope in envelopes {
+            for item in envelope.items() {
+                match item {
+                    EnvelopeItem::Event(event) => self.events.push(event.clone()),
+                    EnvelopeItem::Transaction(transaction) => {
+                        self.transactions.push(transaction.clone())
+                    }
+                    EnvelopeItem::Attachment(attachment) => {
+                        self.attachments.push(attachment.clone())
+                    }
+                    EnvelopeItem::SessionUpdate(session) => self.sessions.push(session.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn normalize_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if VOLATILE_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(format!("[{}]", key));
+                } else {
+                    normalize_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(normalize_value),
+        _ => {}
+    }
+}
+
+/// Handle passed to the closure given to [with_captured()], providing
+/// access to the scoped [Hub] and the data captured so far.
+pub struct CaptureSink {
+    hub: Arc<Hub>,
+    transport: Arc<TestTransport>,
+    breadcrumbs: Arc<Mutex<Vec<Breadcrumb>>>,
+}
+
+impl CaptureSink {
+    /// The [Hub] which is active for the duration of the closure.
+    pub fn hub(&self) -> &Arc<Hub> {
+        &self.hub
+    }
+
+    /// Removes and returns everything which has been captured so far.
+    pub fn take(&self) -> CapturedData {
+        let mut captured = CapturedData {
+            breadcrumbs: std::mem::take(&mut *self.breadcrumbs.lock().unwrap()),
+            ..CapturedData::default()
+        };
+        captured.extend_from_envelopes(self.transport.fetch_and_clear_envelopes());
+        captured
+    }
+}
+
+/// Runs `f` with a scoped subscriber containing a [SentrySpanLayer], and
+/// a scoped [Hub] with a [TracingIntegration] using the default
+/// options, returning everything which was captured.
+///
+/// See [with_captured_options()].
+pub fn with_captured<F: FnOnce(&CaptureSink)>(f: F) -> CapturedData {
+    with_captured_options(TracingIntegrationOptions::default(), f)
+}
+
+/// Runs `f` with a scoped subscriber containing a [SentrySpanLayer], and
+/// a scoped [Hub] with a [TracingIntegration] configured with the
+/// specified `options`, returning everything which was captured.
+///
+/// Nothing is sent over the network, envelopes are collected by a
+/// [TestTransport] instead.
+pub fn with_captured_options<F: FnOnce(&CaptureSink)>(
+    options: TracingIntegrationOptions,
+    f: F,
+) -> CapturedData {
+    let subscriber = tracing_subscriber::registry().with(SentrySpanLayer::default());
+    with_captured_subscriber(options, subscriber, f)
+}
+
+/// Runs `f` with the `subscriber` as the scoped default subscriber,
+/// and a scoped [Hub] with a [TracingIntegration] configured with the
+/// specified `options`, returning everything which was captured.
+pub(crate) fn with_captured_subscriber<S, F>(
+    options: TracingIntegrationOptions,
+    subscriber: S,
+    f: F,
+) -> CapturedData
+where
+    S: Subscriber + Send + Sync + 'static,
+    F: FnOnce(&CaptureSink),
+{
+    with_captured_integration(TracingIntegration::new(options), subscriber, f)
+}
+
+/// Runs `f` with the `subscriber` as the scoped default subscriber,
+/// and a scoped [Hub] with the `integration`, returning everything
+/// which was captured.
+pub(crate) fn with_captured_integration<S, F>(
+    integration: TracingIntegration,
+    subscriber: S,
+    f: F,
+) -> CapturedData
+where
+    S: Subscriber + Send + Sync + 'static,
+    F: FnOnce(&CaptureSink),
+{
+    with_captured_client(ClientOptions::default(), integration, subscriber, f)
+}
+
+/// Runs `f` with the `subscriber` as the scoped default subscriber,
+/// and a scoped [Hub] with a client created from the `client_options`
+/// and the `integration`, returning everything which was captured.
+///
+/// The dsn, transport and `before_breadcrumb` callback of the
+/// `client_options` are replaced to capture the data.
+pub(crate) fn with_captured_client<S, F>(
+    client_options: ClientOptions,
+    integration: TracingIntegration,
+    subscriber: S,
+    f: F,
+) -> CapturedData
+where
+    S: Subscriber + Send + Sync + 'static,
+    F: FnOnce(&CaptureSink),
+{
+    let transport = TestTransport::new();
+    let breadcrumbs: Arc<Mutex<Vec<Breadcrumb>>> = Arc::default();
+
+    let recorded_breadcrumbs = breadcrumbs.clone();
+    let client_options = ClientOptions {
+        dsn: Some(TEST_DSN.parse().expect("Invalid test DSN")),
+        transport: Some(Arc::new(transport.clone())),
+        before_breadcrumb: Some(Arc::new(move |breadcrumb| {
+            recorded_breadcrumbs
+                .lock()
+                .unwrap()
+                .push(breadcrumb.clone());
+            Some(breadcrumb)
+        })),
+        ..client_options
+    }
+    .add_integration(integration);
+
+    let hub = Arc::new(Hub::new(
+        Some(Arc::new(client_options.into())),
+        Arc::default(),
+    ));
+
+    let sink = CaptureSink {
+        hub: hub.clone(),
+        transport,
+        breadcrumbs,
+    };
+
+    tracing::subscriber::with_default(subscriber, || Hub::run(hub, || f(&sink)));
+
+    sink.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_events_and_breadcrumbs() {
+        let captured = with_captured(|_| {
+            tracing::debug!("Filtered out");
+            tracing::info!("Generates a breadcrumb");
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.breadcrumbs.len(), 2);
+        assert_eq!(
+            captured.breadcrumbs[0].message.as_deref(),
+            Some("message=Generates a breadcrumb")
+        );
+    }
+
+    #[test]
+    fn take_removes_what_was_captured_so_far() {
+        let captured = with_captured(|sink| {
+            tracing::error!("First");
+            let first = sink.take();
+            assert_eq!(first.events.len(), 1);
+            assert_eq!(first.breadcrumbs.len(), 1);
+
+            tracing::error!("Second");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("message=Second")
+        );
+    }
+
+    #[test]
+    fn captures_with_options() {
+        let options = TracingIntegrationOptions {
+            emit_breadcrumbs: false,
+            ..TracingIntegrationOptions::default()
+        };
+        let captured = with_captured_options(options, |_| {
+            tracing::error!("Generates an event");
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert!(captured.breadcrumbs.is_empty());
+    }
+
+    #[test]
+    fn normalized_json_pins_volatile_values() {
+        let captured = with_captured(|_| {
+            tracing::error!("Generates an event");
+        });
+
+        let normalized = captured.normalized();
+        let event = &normalized["events"][0];
+        assert_eq!(event["event_id"], "[event_id]");
+        assert_eq!(event["timestamp"], "[timestamp]");
+        assert_eq!(normalized["breadcrumbs"][0]["timestamp"], "[timestamp]");
+        assert_eq!(
+            event["exception"]["values"][0]["value"],
+            "message=Generates an event"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use sentry_core::protocol::{Context, Event, Exception, Level, Map};
+use sentry_core::Hub;
+use tracing::Metadata;
+
+use crate::converters::mark_own_event;
+use crate::export::EventExport;
+use crate::layer::print_dry_run;
+use crate::{Clock, DryRun};
+
+/// A span which is watched for staying open longer than the
+/// [slow_span_threshold](crate::TracingIntegrationOptions::slow_span_threshold).
+struct WatchedSpan {
+    metadata: &'static Metadata<'static>,
+    fields: BTreeMap<String, serde_json::Value>,
+    opened: SystemTime,
+    hub: Arc<Hub>,
+    reported: bool,
+}
+
+/// Keeps track of the open spans, reporting those which stay open
+/// longer than the
+/// [slow_span_threshold](crate::TracingIntegrationOptions::slow_span_threshold)
+/// from a background thread.
+#[derive(Default)]
+pub(crate) struct SpanWatchdog {
+    spans: Mutex<HashMap<u64, WatchedSpan>>,
+    next_id: AtomicU64,
+}
+
+/// Identifies a span watched by a [SpanWatchdog], stored in its
+/// extensions.
+pub(crate) struct WatchedSpanId {
+    watchdog: Weak<SpanWatchdog>,
+    id: u64,
+}
+
+impl WatchedSpanId {
+    /// Replaces the fields reported for the span.
+    pub fn update_fields(&self, fields: BTreeMap<String, serde_json::Value>) {
+        if let Some(watchdog) = self.watchdog.upgrade() {
+            let mut spans = watchdog
+                .spans
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(span) = spans.get_mut(&self.id) {
+                span.fields = fields;
+            }
+        }
+    }
+}
+
+impl Drop for WatchedSpanId {
+    /// Stops watching the span once it is closed.
+    fn drop(&mut self) {
+        if let Some(watchdog) = self.watchdog.upgrade() {
+            watchdog
+                .spans
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&self.id);
+        }
+    }
+}
+
+impl SpanWatchdog {
+    /// Starts the background thread checking the open spans every so
+    /// often, until the watchdog is dropped.
+    ///
+    /// Spans which are open for longer than the `ttl` are considered
+    /// abandoned, and are no longer watched once they have been
+    /// reported.
+    pub fn start(
+        self: &Arc<Self>,
+        threshold: Duration,
+        ttl: Option<Duration>,
+        clock: Arc<dyn Clock>,
+        dry_run: DryRun,
+        export: Arc<EventExport>,
+    ) -> std::io::Result<()> {
+        let watchdog = Arc::downgrade(self);
+        let interval = (threshold / 4).clamp(Duration::from_millis(10), Duration::from_secs(1));
+        thread::Builder::new()
+            .name("sentry-tracing-watchdog".into())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                match watchdog.upgrade() {
+                    Some(watchdog) => watchdog.check(threshold, ttl, clock.now(), dry_run, &export),
+                    None => return,
+                }
+            })
+            .map(|_| ())
+    }
+
+    /// Starts watching a span which was opened at `opened`, reporting
+    /// it on the `hub`.
+    pub fn watch(
+        self: &Arc<Self>,
+        metadata: &'static Metadata<'static>,
+        fields: BTreeMap<String, serde_json::Value>,
+        opened: SystemTime,
+        hub: Arc<Hub>,
+    ) -> WatchedSpanId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let span = WatchedSpan {
+            metadata,
+            fields,
+            opened,
+            hub,
+            reported: false,
+        };
+        self.spans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, span);
+        WatchedSpanId {
+            watchdog: Arc::downgrade(self),
+            id,
+        }
+    }
+
+    /// Reports each span which has been open for at least `threshold`
+    /// at `now`, once, and stops watching those which have been
+    /// reported and open for at least the `ttl`.
+    fn check(
+        &self,
+        threshold: Duration,
+        ttl: Option<Duration>,
+        now: SystemTime,
+        dry_run: DryRun,
+        export: &EventExport,
+    ) {
+        let mut events = Vec::new();
+        {
+            let mut spans = self.spans.lock().unwrap_or_else(PoisonError::into_inner);
+            for span in spans.values_mut().filter(|span| !span.reported) {
+                let duration = now.duration_since(span.opened).unwrap_or_default();
+                if duration >= threshold {
+                    span.reported = true;
+                    events.push((span.hub.clone(), slow_span_event(span, duration, now)));
+                }
+            }
+
+            if let Some(ttl) = ttl {
+                spans.retain(|_, span| {
+                    let duration = now.duration_since(span.opened).unwrap_or_default();
+                    if !span.reported || duration < ttl {
+                        return true;
+                    }
+                    eprintln!(
+                        "sentry-tracing: span `{}` has been open for {:.3}s, and is no longer watched as it seems to be abandoned",
+                        span.metadata.name(),
+                        duration.as_secs_f64()
+                    );
+                    false
+                });
+            }
+        }
+
+        // Capture outside of the lock, as the hub may emit tracing
+        // events which in turn open spans.
+        for (hub, mut event) in events {
+            export.write(&event);
+            if dry_run.print() {
+                print_dry_run("event", &event);
+            }
+            if dry_run.send() {
+                mark_own_event(&mut event);
+                hub.capture_event(event);
+            }
+        }
+    }
+}
+
+/// Creates the warning reporting that the `span` has been open for
+/// `duration`.
+fn slow_span_event(span: &WatchedSpan, duration: Duration, now: SystemTime) -> Event<'static> {
+    let metadata = span.metadata;
+
+    let mut slow_span = Map::new();
+    slow_span.insert("name".into(), metadata.name().into());
+    slow_span.insert("target".into(), metadata.target().into());
+    slow_span.insert(
+        "duration_ms".into(),
+        (duration.as_secs_f64() * 1000.0).into(),
+    );
+    slow_span.insert("fields".into(), serde_json::json!(span.fields));
+
+    let mut contexts = Map::new();
+    contexts.insert("slow_span".into(), Context::Other(slow_span));
+
+    Event {
+        logger: Some(metadata.target().to_owned()),
+        fingerprint: vec![
+            "slow span".into(),
+            metadata.target().to_owned().into(),
+            metadata.name().into(),
+        ]
+        .into(),
+        timestamp: now.into(),
+        level: Level::Warning,
+        exception: vec![Exception {
+            ty: format!("[{}] slow span", metadata.target()),
+            value: Some(format!(
+                "span `{}` has been open for {:.3}s",
+                metadata.name(),
+                duration.as_secs_f64()
+            )),
+            module: metadata.module_path().map(|p| p.to_owned()),
+            ..Default::default()
+        }]
+        .into(),
+        contexts,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use super::SpanWatchdog;
+    use crate::export::EventExport;
+    use crate::test::with_captured;
+    use crate::DryRun;
+
+    #[test]
+    fn spans_are_reported_before_they_are_abandoned() {
+        let watchdog = Arc::new(SpanWatchdog::default());
+        let opened = SystemTime::UNIX_EPOCH;
+        let threshold = Duration::from_secs(10);
+        let ttl = Some(Duration::from_secs(5));
+        let export = EventExport::default();
+
+        let captured = with_captured(|sink| {
+            let span = tracing::info_span!("job");
+            let metadata = span.metadata().expect("the span is enabled");
+            let _id = watchdog.watch(metadata, BTreeMap::new(), opened, sink.hub().clone());
+
+            let check = |elapsed| {
+                watchdog.check(threshold, ttl, opened + elapsed, DryRun::Disabled, &export)
+            };
+            check(Duration::from_secs(6));
+            assert_eq!(watchdog.spans.lock().unwrap().len(), 1);
+            check(Duration::from_secs(11));
+            assert!(watchdog.spans.lock().unwrap().is_empty());
+        });
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(
+            captured.events[0].exception.values[0].value.as_deref(),
+            Some("span `job` has been open for 11.000s")
+        );
+    }
+}